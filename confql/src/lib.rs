@@ -55,7 +55,8 @@
 //!
 //! // The `Ctx` struct has been generated for us, implementing
 //! // `juniper::Context`.  All it needs to initialize is a `PathBuf`
-//! // pointing at the root of the data directory.
+//! // pointing at the root of the data directory, or else `Ctx::new`
+//! // takes a `DataResolver` configured with its builder methods.
 //! let ctx = Ctx::from(mocks.path().to_path_buf());
 //!
 //! // Run the executor.
@@ -91,4 +92,6 @@
 #![deny(missing_docs, rustdoc::missing_doc_code_examples)]
 pub use confql_data_resolver;
 pub use confql_proc_macro::{graphql_schema, graphql_schema_from_file};
+#[cfg(feature = "futures")]
+pub use futures;
 pub use serde_yaml;
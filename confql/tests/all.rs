@@ -1,12 +1,28 @@
 #[test]
 fn tests() {
     let t = trybuild::TestCases::new();
+    t.pass("tests/array_identifier_merges_across_files.rs");
+    #[cfg(feature = "async")]
+    t.pass("tests/async_resolvers.rs");
     t.pass("tests/executable_schema.rs");
+    t.pass("tests/field_sources.rs");
     t.pass("tests/file_name_as_array_field.rs");
     t.pass("tests/file_name_as_array_field_overrides.rs");
+    #[cfg(feature = "git")]
+    t.pass("tests/git_revisions.rs");
     t.pass("tests/graphql_schema_macro.rs");
     t.pass("tests/happy_with_all_types.rs");
     t.pass("tests/hash_key_as_array_field.rs");
+    t.pass("tests/indexed_data_dir.rs");
+    t.compile_fail("tests/ui/invalid_directives.rs");
+    t.pass("tests/layered_roots.rs");
+    t.pass("tests/lazy_objects.rs");
+    t.pass("tests/merge_strategies.rs");
+    t.pass("tests/ordered_lists.rs");
+    t.pass("tests/profile_overlays.rs");
     t.pass("tests/queryable_schema.rs");
     t.pass("tests/renders_types_as_structs.rs");
+    t.pass("tests/strict_mode.rs");
+    #[cfg(feature = "watch")]
+    t.pass("tests/subscriptions.rs");
 }
@@ -0,0 +1,87 @@
+use color_eyre::Result;
+use confql_proc_macro::graphql_schema;
+use indoc::indoc;
+use juniper::{graphql_value, EmptyMutation, EmptySubscription};
+use test_files::TestFiles;
+
+graphql_schema! {
+    type Thing {
+        name: String! @confql(arrayIdentifier: true)
+        size: Float!
+        colour: String
+    }
+
+    type Query {
+        things: [Thing!]!
+    }
+
+    schema {
+        query: Query
+    }
+}
+
+fn main() -> Result<()> {
+    let mocks = TestFiles::new();
+    mocks
+        .file(
+            "index.yml",
+            indoc! {"
+                ---
+                things:
+                - name: gadget
+                  size: 3.3
+                - name: widget
+                  size: 0.1
+            "},
+        )
+        .file(
+            "things.yml",
+            indoc! {"
+                ---
+                widget:
+                    colour: red
+                dongle:
+                    size: 2.2
+            "},
+        )
+        .file(
+            "things/widget.yml",
+            indoc! {"
+                ---
+                size: 1.1
+            "},
+        );
+
+    let ctx = Ctx::from(mocks.path().to_path_buf());
+
+    // Run the executor.
+    let (res, _errors) = juniper::execute_sync(
+        indoc! {"
+            {
+                things {
+                    name
+                    size
+                    colour
+                }
+            }"},
+        None,
+        &Schema::new(Query, EmptyMutation::new(), EmptySubscription::new()),
+        &juniper::Variables::new(),
+        &ctx,
+    )
+    .unwrap();
+
+    // Ensure the value matches.
+    assert_eq!(
+        res,
+        graphql_value!({
+            "things": [
+                {"name": "gadget", "size": 3.3, "colour": None},
+                {"name": "widget", "size": 1.1, "colour": "red"},
+                {"name": "dongle", "size": 2.2, "colour": None}
+            ]
+        })
+    );
+
+    Ok(())
+}
@@ -0,0 +1,123 @@
+#![cfg(feature = "async")]
+use color_eyre::Result;
+use confql_proc_macro::graphql_schema;
+use futures::executor::block_on;
+use indoc::indoc;
+use juniper::graphql_value;
+use test_files::TestFiles;
+
+graphql_schema! {
+    type User @confql(lazy: true, sources: true) {
+        name: String!
+    }
+
+    type Team @confql(lazy: true) {
+        lead: User!
+        deputy: User
+        tags: [String!]!
+    }
+
+    type Service {
+        name: String! @confql(arrayIdentifier: true)
+        port: Int
+    }
+
+    type Query {
+        services: [Service!]! @confql(orderBy: "port")
+        tags: [String!]! @confql(merge: UNIQUE)
+        team: Team!
+        version: String
+    }
+
+    schema @confql(async: true) {
+        query: Query
+    }
+}
+
+fn main() -> Result<()> {
+    let mocks = TestFiles::new();
+    mocks
+        .file(
+            "index.yml",
+            indoc! {"
+                ---
+                version: 1.2.3
+                tags: [a, b]
+                team:
+                    lead:
+                        name: Bill
+                    tags: [y, x]
+            "},
+        )
+        .file(
+            "tags.yml",
+            indoc! {"
+                ---
+                [b, c]
+            "},
+        )
+        .file(
+            "services/api.yml",
+            indoc! {"
+                ---
+                port: 8080
+            "},
+        )
+        .file(
+            "services/web.yml",
+            indoc! {"
+                ---
+                port: 80
+            "},
+        );
+
+    let ctx = Ctx::from(mocks.path().to_path_buf());
+
+    let (res, errors) = block_on(juniper::execute(
+        indoc! {"
+            {
+                services {
+                    name
+                }
+                tags
+                team {
+                    lead {
+                        name
+                        _sources {
+                            files
+                        }
+                    }
+                    deputy {
+                        name
+                    }
+                    tags
+                }
+                version
+            }"},
+        None,
+        &schema(),
+        &juniper::Variables::new(),
+        &ctx,
+    ))
+    .unwrap();
+
+    assert!(errors.is_empty());
+    assert_eq!(
+        res,
+        graphql_value!({
+            "services": [{"name": "web"}, {"name": "api"}],
+            "tags": ["a", "b", "c"],
+            "team": {
+                "lead": {
+                    "name": "Bill",
+                    "_sources": [{"files": ["index.yml"]}],
+                },
+                "deputy": None,
+                "tags": ["y", "x"],
+            },
+            "version": "1.2.3",
+        })
+    );
+
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use color_eyre::Result;
+use confql_proc_macro::graphql_schema;
+use indoc::indoc;
+use juniper::{graphql_value, EmptyMutation, EmptySubscription};
+use test_files::TestFiles;
+
+graphql_schema! {
+    type Port {
+        number: Int!
+    }
+
+    type Service @confql(sources: true) {
+        name: String!
+        port: Int!
+        ports: [Port!]! @confql(merge: UNIQUE)
+        tags: [String!]!
+    }
+
+    type Query {
+        service: Service!
+    }
+
+    schema {
+        query: Query
+    }
+}
+
+fn main() -> Result<()> {
+    let mocks = TestFiles::new();
+    mocks
+        .file(
+            "index.yml",
+            indoc! {"
+                ---
+                service:
+                    name: api
+                    port: 80
+                    ports:
+                        - number: 80
+            "},
+        )
+        .file(
+            "service.yml",
+            indoc! {"
+                ---
+                port: 8080
+                ports:
+                    - number: 80
+                    - number: 443
+            "},
+        )
+        .file(
+            "service/tags.yml",
+            indoc! {"
+                ---
+                [internal]
+            "},
+        );
+
+    let ctx = Ctx::from(mocks.path().to_path_buf());
+
+    // Run the executor.
+    let (res, _errors) = juniper::execute_sync(
+        indoc! {"
+            {
+                service {
+                    port
+                    ports { number }
+                    _sources {
+                        field
+                        files
+                    }
+                }
+            }"},
+        None,
+        &Schema::new(Query, EmptyMutation::new(), EmptySubscription::new()),
+        &juniper::Variables::new(),
+        &ctx,
+    )
+    .unwrap();
+
+    // Ensure the value matches.
+    assert_eq!(
+        res,
+        graphql_value!({
+            "service": {
+                "port": 8080,
+                "ports": [{"number": 80}, {"number": 443}],
+                "_sources": [
+                    {"field": "name", "files": ["index.yml"]},
+                    {"field": "port", "files": ["index.yml", "service.yml"]},
+                    {"field": "ports", "files": ["index.yml", "service.yml"]},
+                    {"field": "tags", "files": ["service/tags.yml"]}
+                ]
+            }
+        })
+    );
+
+    Ok(())
+}
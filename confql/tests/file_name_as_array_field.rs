@@ -1,7 +1,7 @@
 use color_eyre::Result;
 use confql_proc_macro::graphql_schema;
 use indoc::indoc;
-use juniper::{graphql_value, EmptyMutation, EmptySubscription, Value};
+use juniper::{graphql_value, EmptyMutation, EmptySubscription};
 use test_files::TestFiles;
 
 graphql_schema! {
@@ -40,7 +40,7 @@ fn main() -> Result<()> {
     let ctx = Ctx::from(mocks.path().to_path_buf());
 
     // Run the executor.
-    let (mut res, _errors) = juniper::execute_sync(
+    let (res, _errors) = juniper::execute_sync(
         indoc! {"
             {
                 things {
@@ -55,30 +55,6 @@ fn main() -> Result<()> {
     )
     .unwrap();
 
-    let things = res
-        .as_mut_object_value()
-        .unwrap()
-        .get_mut_field_value("things")
-        .unwrap();
-    let mut new_things = things.as_list_value().unwrap().clone();
-    new_things.sort_by(|a, b| {
-        a.as_object_value()
-            .unwrap()
-            .get_field_value("name")
-            .unwrap()
-            .as_string_value()
-            .unwrap()
-            .cmp(
-                b.as_object_value()
-                    .unwrap()
-                    .get_field_value("name")
-                    .unwrap()
-                    .as_string_value()
-                    .unwrap(),
-            )
-    });
-    *things = Value::list(new_things);
-
     // Ensure the value matches.
     assert_eq!(
         res,
@@ -0,0 +1,127 @@
+#![cfg(feature = "git")]
+use color_eyre::Result;
+use confql_proc_macro::graphql_schema;
+use git2::{IndexAddOption, Repository, Signature};
+use indoc::indoc;
+use juniper::graphql_value;
+use test_files::TestFiles;
+
+graphql_schema! {
+    type Team @confql(lazy: true) {
+        lead: String!
+    }
+
+    type Service {
+        name: String! @confql(arrayIdentifier: true)
+        port: Int!
+    }
+
+    type Query {
+        services: [Service!]!
+        team: Team
+    }
+
+    schema {
+        query: Query
+    }
+}
+
+fn commit(repo: &Repository) -> Result<()> {
+    let mut index = repo.index()?;
+    index.add_all(["*"], IndexAddOption::DEFAULT, None)?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let signature = Signature::now("test", "test@example.com")?;
+    let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+    let parents: Vec<_> = parent.iter().collect();
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        "data",
+        &tree,
+        &parents,
+    )?;
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let mocks = TestFiles::new();
+    let repo = Repository::init(mocks.path())?;
+    mocks
+        .file(
+            "services/api.yml",
+            indoc! {"
+                ---
+                port: 80
+            "},
+        )
+        .file(
+            "team.yml",
+            indoc! {"
+                ---
+                lead: Bill
+            "},
+        );
+    commit(&repo)?;
+    repo.branch("release", &repo.head()?.peel_to_commit()?, false)?;
+    mocks
+        .file(
+            "services/api.yml",
+            indoc! {"
+                ---
+                port: 8080
+            "},
+        )
+        .file(
+            "team.yml",
+            indoc! {"
+                ---
+                lead: Will
+            "},
+        );
+
+    let query = indoc! {"
+        {
+            services {
+                name
+                port
+            }
+            team {
+                lead
+            }
+            release: services(asOf: \"release\") {
+                port
+            }
+            releaseTeam: team(asOf: \"release\") {
+                lead
+            }
+        }"};
+    let ctx = Ctx::from(mocks.path().to_path_buf());
+    let (res, errors) =
+        juniper::execute_sync(query, None, &schema(), &juniper::Variables::new(), &ctx).unwrap();
+
+    assert!(errors.is_empty());
+    assert_eq!(
+        res,
+        graphql_value!({
+            "services": [{"name": "api", "port": 8080}],
+            "team": {"lead": "Will"},
+            "release": [{"port": 80}],
+            "releaseTeam": {"lead": "Bill"},
+        })
+    );
+
+    // a context may also read from a revision throughout
+    let ctx = Ctx::new(DataResolver::from(mocks.path().to_path_buf()).at_revision("release")?);
+    let (res, _errors) = juniper::execute_sync(
+        "{ services { port } }",
+        None,
+        &schema(),
+        &juniper::Variables::new(),
+        &ctx,
+    )
+    .unwrap();
+    assert_eq!(res, graphql_value!({"services": [{"port": 80}]}));
+
+    Ok(())
+}
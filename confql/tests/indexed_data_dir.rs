@@ -0,0 +1,78 @@
+use color_eyre::Result;
+use confql_proc_macro::graphql_schema;
+use indoc::indoc;
+use juniper::{graphql_value, EmptyMutation, EmptySubscription};
+use test_files::TestFiles;
+
+graphql_schema! {
+    type Service {
+        name: String!
+        port: Int
+    }
+
+    type Query {
+        services: [Service!]!
+    }
+
+    schema {
+        query: Query
+    }
+}
+
+fn main() -> Result<()> {
+    let mocks = TestFiles::new();
+    mocks.file(
+        "services/api.yml",
+        indoc! {"
+            ---
+            name: api
+            port: 80
+        "},
+    );
+
+    let query = indoc! {"
+        {
+            services {
+                name
+                port
+            }
+        }"};
+    let schema = Schema::new(Query, EmptyMutation::new(), EmptySubscription::new());
+    let ctx = Ctx::new(DataResolver::from(mocks.path().to_path_buf()).indexed(true));
+    let (res, _errors) =
+        juniper::execute_sync(query, None, &schema, &juniper::Variables::new(), &ctx).unwrap();
+    assert_eq!(
+        res,
+        graphql_value!({
+            "services": [{"name": "api", "port": 80}]
+        })
+    );
+
+    // New files are seen once the index is refreshed.
+    mocks.file(
+        "services/db.yml",
+        indoc! {"
+            ---
+            name: db
+        "},
+    );
+    let (res, _errors) =
+        juniper::execute_sync(query, None, &schema, &juniper::Variables::new(), &ctx).unwrap();
+    assert_eq!(
+        res,
+        graphql_value!({
+            "services": [{"name": "api", "port": 80}]
+        })
+    );
+    ctx.data_resolver.refresh_index();
+    let (res, _errors) =
+        juniper::execute_sync(query, None, &schema, &juniper::Variables::new(), &ctx).unwrap();
+    assert_eq!(
+        res,
+        graphql_value!({
+            "services": [{"name": "api", "port": 80}, {"name": "db", "port": None}]
+        })
+    );
+
+    Ok(())
+}
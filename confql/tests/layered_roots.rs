@@ -0,0 +1,87 @@
+use color_eyre::Result;
+use confql_proc_macro::graphql_schema;
+use indoc::indoc;
+use juniper::{graphql_value, EmptyMutation, EmptySubscription};
+use test_files::TestFiles;
+
+graphql_schema! {
+    type Service {
+        name: String! @confql(arrayIdentifier: true)
+        port: Int
+        replicas: Int
+    }
+
+    type Query {
+        services: [Service!]!
+        owner: String
+    }
+
+    schema {
+        query: Query
+    }
+}
+
+fn main() -> Result<()> {
+    let mocks = TestFiles::new();
+    mocks
+        .file(
+            "base/index.yml",
+            indoc! {"
+                ---
+                owner: ops
+            "},
+        )
+        .file(
+            "base/services/api.yml",
+            indoc! {"
+                ---
+                port: 80
+                replicas: 1
+            "},
+        )
+        .file(
+            "base/services/web.yml",
+            indoc! {"
+                ---
+                port: 8080
+                replicas: 1
+            "},
+        )
+        .file(
+            "prod/services.yml",
+            indoc! {"
+                ---
+                api:
+                    replicas: 3
+            "},
+        );
+
+    let query = indoc! {"
+        {
+            services {
+                name
+                port
+                replicas
+            }
+            owner
+        }"};
+    let schema = Schema::new(Query, EmptyMutation::new(), EmptySubscription::new());
+    let ctx = Ctx::new(DataResolver::from(vec![
+        mocks.path().join("base"),
+        mocks.path().join("prod"),
+    ]));
+    let (res, errors) =
+        juniper::execute_sync(query, None, &schema, &juniper::Variables::new(), &ctx).unwrap();
+    assert_eq!(
+        res,
+        graphql_value!({
+            "services": [
+                {"name": "api", "port": 80, "replicas": 3},
+                {"name": "web", "port": 8080, "replicas": 1},
+            ],
+            "owner": "ops"
+        })
+    );
+    assert!(errors.is_empty());
+    Ok(())
+}
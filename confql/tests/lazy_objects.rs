@@ -0,0 +1,113 @@
+use color_eyre::Result;
+use confql_proc_macro::graphql_schema;
+use indoc::indoc;
+use juniper::graphql_value;
+use test_files::TestFiles;
+
+graphql_schema! {
+    type User @confql(lazy: true) {
+        name: String!
+    }
+
+    type Team @confql(lazy: true) {
+        name: String! @confql(arrayIdentifier: true)
+        lead: User!
+        deputy: User
+        members: [User!]! @confql(orderBy: "name")
+    }
+
+    type Query {
+        team: Team!
+        teams: [Team!]!
+    }
+
+    schema {
+        query: Query
+    }
+}
+
+fn main() -> Result<()> {
+    let mocks = TestFiles::new();
+    mocks
+        .file(
+            "team.yml",
+            indoc! {"
+                ---
+                name: backend
+                lead:
+                    name: Bill
+            "},
+        )
+        .file(
+            "team/members.yml",
+            indoc! {"
+                ---
+                - name: [unterminated
+            "},
+        )
+        .file(
+            "teams/frontend.yml",
+            indoc! {"
+                ---
+                lead:
+                    name: Will
+                members:
+                    - name: Zed
+                    - name: Ann
+            "},
+        );
+
+    // Strict, so only data actually read for the query could raise errors
+    let ctx = Ctx::new(DataResolver::from(mocks.path().to_path_buf()).strict(true));
+
+    let (res, errors) = juniper::execute_sync(
+        indoc! {"
+            {
+                team {
+                    name
+                    lead {
+                        name
+                    }
+                    deputy {
+                        name
+                    }
+                }
+                teams {
+                    name
+                    lead {
+                        name
+                    }
+                    members {
+                        name
+                    }
+                }
+            }"},
+        None,
+        &schema(),
+        &juniper::Variables::new(),
+        &ctx,
+    )
+    .unwrap();
+
+    // The malformed members file is never read
+    assert!(errors.is_empty());
+    assert_eq!(
+        res,
+        graphql_value!({
+            "team": {
+                "name": "backend",
+                "lead": {"name": "Bill"},
+                "deputy": None,
+            },
+            "teams": [
+                {
+                    "name": "frontend",
+                    "lead": {"name": "Will"},
+                    "members": [{"name": "Ann"}, {"name": "Zed"}],
+                }
+            ]
+        })
+    );
+
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use color_eyre::Result;
+use confql_proc_macro::graphql_schema;
+use indoc::indoc;
+use juniper::{graphql_value, EmptyMutation, EmptySubscription};
+use test_files::TestFiles;
+
+graphql_schema! {
+    type Service {
+        name: String! @confql(arrayIdentifier: true)
+        tags: [String!]! @confql(merge: REPLACE)
+        ports: [Int!]! @confql(merge: UNIQUE)
+    }
+
+    type Query {
+        defaults: Service!
+        services: [Service!]! @confql(merge: BY_KEY)
+        tags: [String!] @confql(merge: UNIQUE)
+    }
+
+    schema {
+        query: Query
+    }
+}
+
+fn main() -> Result<()> {
+    let mocks = TestFiles::new();
+    mocks
+        .file(
+            "index.yml",
+            indoc! {"
+                ---
+                defaults:
+                    name: default
+                    tags: [a, b]
+                    ports: [80, 443]
+                services:
+                - name: api
+                  tags: [internal]
+                  ports: [80]
+                tags: [x, y]
+            "},
+        )
+        .file(
+            "defaults.yml",
+            indoc! {"
+                ---
+                tags: [c]
+                ports: [443, 8080]
+            "},
+        )
+        .file(
+            "services.yml",
+            indoc! {"
+                ---
+                - name: api
+                  ports: [80, 8080]
+                - name: web
+                  tags: [public]
+                  ports: [443]
+            "},
+        )
+        .file(
+            "tags.yml",
+            indoc! {"
+                ---
+                [y, z]
+            "},
+        );
+
+    let ctx = Ctx::from(mocks.path().to_path_buf());
+
+    // Run the executor.
+    let (res, _errors) = juniper::execute_sync(
+        indoc! {"
+            {
+                defaults {
+                    tags
+                    ports
+                }
+                services {
+                    name
+                    tags
+                    ports
+                }
+                tags
+            }"},
+        None,
+        &Schema::new(Query, EmptyMutation::new(), EmptySubscription::new()),
+        &juniper::Variables::new(),
+        &ctx,
+    )
+    .unwrap();
+
+    // Ensure the value matches.
+    assert_eq!(
+        res,
+        graphql_value!({
+            "defaults": {"tags": ["c"], "ports": [80, 443, 8080]},
+            "services": [
+                {"name": "api", "tags": ["internal"], "ports": [80, 8080]},
+                {"name": "web", "tags": ["public"], "ports": [443]}
+            ],
+            "tags": ["x", "y", "z"]
+        })
+    );
+
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use color_eyre::Result;
+use confql_proc_macro::graphql_schema;
+use indoc::indoc;
+use juniper::{graphql_value, EmptyMutation, EmptySubscription};
+use test_files::TestFiles;
+
+graphql_schema! {
+    type Host {
+        name: String! @confql(arrayIdentifier: true)
+        rank: Int!
+    }
+
+    type Group {
+        hosts: [Host!]! @confql(orderBy: "rank")
+    }
+
+    type Query {
+        hosts: [Host!]!
+        ranked: [Host!]! @confql(orderBy: "rank")
+        group: Group!
+    }
+
+    schema {
+        query: Query
+    }
+}
+
+fn main() -> Result<()> {
+    let mocks = TestFiles::new();
+    mocks
+        .file("hosts/a.yml", "rank: 3")
+        .file("hosts/b.yml", "rank: 2")
+        .file("hosts/c.yml", "rank: 1")
+        .file(
+            "hosts/_order.yml",
+            indoc! {"
+                ---
+                - c
+            "},
+        )
+        .file("ranked/x.yml", "rank: 2")
+        .file("ranked/y.yml", "rank: 1")
+        .file("group/hosts/p.yml", "rank: 2")
+        .file("group/hosts/q.yml", "rank: 1");
+
+    let ctx = Ctx::from(mocks.path().to_path_buf());
+
+    // Run the executor.
+    let (res, _errors) = juniper::execute_sync(
+        indoc! {"
+            {
+                hosts {
+                    name
+                }
+                ranked {
+                    name
+                }
+                group {
+                    hosts {
+                        name
+                    }
+                }
+            }"},
+        None,
+        &Schema::new(Query, EmptyMutation::new(), EmptySubscription::new()),
+        &juniper::Variables::new(),
+        &ctx,
+    )
+    .unwrap();
+
+    // Ensure the value matches.
+    assert_eq!(
+        res,
+        graphql_value!({
+            "hosts": [{"name": "c"}, {"name": "a"}, {"name": "b"}],
+            "ranked": [{"name": "y"}, {"name": "x"}],
+            "group": {"hosts": [{"name": "q"}, {"name": "p"}]}
+        })
+    );
+
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use color_eyre::Result;
+use confql_proc_macro::graphql_schema;
+use indoc::indoc;
+use juniper::{graphql_value, EmptyMutation, EmptySubscription};
+use test_files::TestFiles;
+
+graphql_schema! {
+    type Service {
+        name: String! @confql(arrayIdentifier: true)
+        replicas: Int
+    }
+
+    type Query {
+        services: [Service!]!
+        owner: String
+    }
+
+    schema @confql(profiles: true) {
+        query: Query
+    }
+}
+
+fn main() -> Result<()> {
+    let mocks = TestFiles::new();
+    mocks
+        .file(
+            "index.yml",
+            indoc! {"
+                ---
+                owner: ops
+            "},
+        )
+        .file(
+            "index.prod.yml",
+            indoc! {"
+                ---
+                owner: sre
+            "},
+        )
+        .file(
+            "services/api.yml",
+            indoc! {"
+                ---
+                replicas: 1
+            "},
+        )
+        .file(
+            "services/api.prod.yml",
+            indoc! {"
+                ---
+                replicas: 3
+            "},
+        )
+        .file(
+            "services/web.yml",
+            indoc! {"
+                ---
+                replicas: 1
+            "},
+        );
+
+    let schema = Schema::new(Query, EmptyMutation::new(), EmptySubscription::new());
+    let ctx = Ctx::new(DataResolver::from(mocks.path().to_path_buf()).with_profiles(["prod"]));
+    for (query, expected) in [
+        (
+            indoc! {"
+                {
+                    services { name replicas }
+                    owner
+                }"},
+            graphql_value!({
+                "services": [
+                    {"name": "api", "replicas": 1},
+                    {"name": "web", "replicas": 1},
+                ],
+                "owner": "ops"
+            }),
+        ),
+        (
+            indoc! {"
+                {
+                    services(profile: \"prod\") { name replicas }
+                    owner(profile: \"prod\")
+                }"},
+            graphql_value!({
+                "services": [
+                    {"name": "api", "replicas": 3},
+                    {"name": "web", "replicas": 1},
+                ],
+                "owner": "sre"
+            }),
+        ),
+    ] {
+        let (res, errors) =
+            juniper::execute_sync(query, None, &schema, &juniper::Variables::new(), &ctx).unwrap();
+        assert_eq!(res, expected);
+        assert!(errors.is_empty());
+    }
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use color_eyre::Result;
+use confql_proc_macro::graphql_schema;
+use indoc::indoc;
+use juniper::{graphql_value, EmptyMutation, EmptySubscription};
+use test_files::TestFiles;
+
+graphql_schema! {
+    type Service {
+        name: String!
+        port: Int
+    }
+
+    type Query {
+        service: Service
+        owner: String
+    }
+
+    schema {
+        query: Query
+    }
+}
+
+fn main() -> Result<()> {
+    let mocks = TestFiles::new();
+    mocks
+        .file(
+            "index.yml",
+            indoc! {"
+                ---
+                service:
+                    name: api
+                owner: ops
+            "},
+        )
+        .file(
+            "service/port.yml",
+            indoc! {"
+                ---
+                [80
+            "},
+        );
+
+    let query = indoc! {"
+        {
+            service {
+                name
+                port
+            }
+            owner
+        }"};
+    let schema = Schema::new(Query, EmptyMutation::new(), EmptySubscription::new());
+
+    // By default the malformed file is skipped.
+    let ctx = Ctx::from(mocks.path().to_path_buf());
+    let (res, errors) =
+        juniper::execute_sync(query, None, &schema, &juniper::Variables::new(), &ctx).unwrap();
+    assert_eq!(
+        res,
+        graphql_value!({
+            "service": {"name": "api", "port": None},
+            "owner": "ops"
+        })
+    );
+    assert!(errors.is_empty());
+
+    // In strict mode it fails the field resolved from it.
+    let ctx = Ctx::new(DataResolver::from(mocks.path().to_path_buf()).strict(true));
+    let (res, errors) =
+        juniper::execute_sync(query, None, &schema, &juniper::Variables::new(), &ctx).unwrap();
+    assert_eq!(
+        res,
+        graphql_value!({
+            "service": None,
+            "owner": "ops"
+        })
+    );
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0]
+        .error()
+        .message()
+        .starts_with("Errors resolving data:"));
+
+    Ok(())
+}
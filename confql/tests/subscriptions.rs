@@ -0,0 +1,78 @@
+#![cfg(feature = "watch")]
+use color_eyre::Result;
+use confql::futures::{executor::block_on, StreamExt};
+use confql_proc_macro::graphql_schema;
+use indoc::indoc;
+use juniper::{graphql_value, Value};
+use test_files::TestFiles;
+
+graphql_schema! {
+    type Service {
+        name: String! @confql(arrayIdentifier: true)
+        port: Int
+    }
+
+    type Query {
+        services: [Service!]!
+    }
+
+    type Subscription {
+        services: [Service!]! @confql(orderBy: "port")
+    }
+
+    schema {
+        query: Query
+        subscription: Subscription
+    }
+}
+
+fn main() -> Result<()> {
+    let mocks = TestFiles::new();
+    mocks.file(
+        "services/api.yml",
+        indoc! {"
+            ---
+            port: 80
+        "},
+    );
+
+    let schema = schema();
+    let ctx = Ctx::from(mocks.path().to_path_buf());
+    let (res, errors) = block_on(juniper::resolve_into_stream(
+        "subscription { services { name port } }",
+        None,
+        &schema,
+        &juniper::Variables::new(),
+        &ctx,
+    ))
+    .unwrap();
+    assert!(errors.is_empty());
+    let mut services = match res {
+        Value::Object(fields) => match fields.into_iter().next() {
+            Some((_, Value::Scalar(stream))) => stream,
+            _ => panic!("expected a stream"),
+        },
+        _ => panic!("expected an object of streams"),
+    };
+
+    // The current value comes first.
+    assert_eq!(
+        block_on(services.next()).unwrap().unwrap(),
+        graphql_value!([{"name": "api", "port": 80}])
+    );
+
+    // Then the value again each time its data changes.
+    mocks.file(
+        "services/db.yml",
+        indoc! {"
+            ---
+            port: 5432
+        "},
+    );
+    assert_eq!(
+        block_on(services.next()).unwrap().unwrap(),
+        graphql_value!([{"name": "api", "port": 80}, {"name": "db", "port": 5432}])
+    );
+
+    Ok(())
+}
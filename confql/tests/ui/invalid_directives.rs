@@ -0,0 +1,13 @@
+use confql_proc_macro::graphql_schema;
+
+graphql_schema! {
+    type Query {
+        tags: [String!] @confql(merge: SOMETIMES)
+    }
+
+    schema {
+        query: Query
+    }
+}
+
+fn main() {}
@@ -0,0 +1,54 @@
+//! Resolves a directory of many list item files.
+//!
+//! Compare sequential and parallel resolution with criterion baselines:
+//!
+//! ```sh
+//! cargo bench --bench list_dirs -- --save-baseline sequential
+//! cargo bench --bench list_dirs --features parallel -- --baseline sequential
+//! ```
+use confql_data_resolver::{DataPath, DataResolver, ResolveValue};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use serde_yaml::{Mapping, Value};
+use test_files::TestFiles;
+
+/// List item named after its file
+struct Host;
+
+impl ResolveValue for Host {
+    fn identifier_fields() -> &'static [&'static str] {
+        &["name"]
+    }
+    fn init_with_identifier(identifier: Value) -> Value {
+        let mut mapping = Mapping::new();
+        mapping.insert(Value::from("name"), identifier);
+        Value::Mapping(mapping)
+    }
+    fn resolve_vec_base(data_path: &DataPath) -> Value {
+        match data_path.file_stem().and_then(|s| s.to_str()) {
+            Some(file_stem) => Self::init_with_identifier(file_stem.into()),
+            None => Value::Null,
+        }
+    }
+}
+
+fn hosts(c: &mut Criterion) {
+    let mut group = c.benchmark_group("hosts");
+    group.sample_size(10);
+    for count in [100, 1000] {
+        let mocks = TestFiles::new();
+        for i in 0..count {
+            mocks.file(
+                &format!("hosts/host-{:04}.yml", i),
+                &format!("address: 10.0.{}.{}\ntags: [a, b, c]", i / 256, i % 256),
+            );
+        }
+        let resolver = DataResolver::from(mocks.path().to_path_buf());
+        group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, _| {
+            b.iter(|| resolver.resolve::<Vec<Host>>(&["hosts"]).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, hosts);
+criterion_main!(benches);
@@ -0,0 +1,216 @@
+//! Parsed data file caching.
+//!
+//! Resolving an address reads each ancestor data file (e.g. `index.yml`) once for
+//! every field beneath it, so a [DataResolver](crate::DataResolver) keeps the
+//! documents parsed from each file in a [FileCache], shared by all its resolutions.
+//! An entry is reused for as long as its file's [version](crate::FileVersion),
+//! i.e. modification time and size, is unchanged.  The same goes for the rules
+//! built from each `.confqlignore` file, consulted whenever a directory is listed.
+use ignore::gitignore::Gitignore;
+use serde_yaml::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use super::{DataResolverError, DataSource, FileVersion};
+
+struct Entry {
+    version: FileVersion,
+    documents: Vec<Value>,
+}
+
+/// Documents parsed from data files, and ignore rules built from ignore files, keyed
+/// by path.
+#[derive(Default)]
+pub struct FileCache {
+    entries: Mutex<HashMap<PathBuf, Entry>>,
+    ignores: Mutex<HashMap<PathBuf, (FileVersion, Arc<Gitignore>)>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+/// Counts of [FileCache] lookups.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CacheMetrics {
+    /// Lookups answered from the cache
+    pub hits: u64,
+    /// Lookups which had to read the file
+    pub misses: u64,
+    /// Files currently cached
+    pub entries: usize,
+}
+
+impl CacheMetrics {
+    /// Proportion of lookups answered from the cache, or 0 if there were none.
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+impl FileCache {
+    /// Creates an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Empties the cache, keeping its metrics.
+    pub fn clear(&self) {
+        self.entries().clear();
+        self.ignores().clear();
+    }
+    /// Returns the documents of the file at `path` in `source`, from the cache if the
+    /// file is unchanged since they were cached, or else by calling `read` and caching
+    /// the result if successful.
+    pub fn documents<F>(
+        &self,
+        path: &Path,
+        source: &dyn DataSource,
+        read: F,
+    ) -> Result<Vec<Value>, DataResolverError>
+    where
+        F: FnOnce(&Path) -> Result<Vec<Value>, DataResolverError>,
+    {
+        let version = source.version(path)?;
+        if let Some(entry) = self.entries().get(path) {
+            if entry.version == version {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(entry.documents.clone());
+            }
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let documents = read(path)?;
+        self.entries().insert(
+            path.to_path_buf(),
+            Entry {
+                version,
+                documents: documents.clone(),
+            },
+        );
+        Ok(documents)
+    }
+    /// Returns the rules of the ignore file at `path` in `source`, from the cache if
+    /// the file is unchanged since they were cached, or else by calling `build`.
+    /// Rules are only cached if `build` reports them complete, so that a file with
+    /// bad patterns is reported every time it is consulted.
+    pub(crate) fn ignore_rules<F>(
+        &self,
+        path: &Path,
+        source: &dyn DataSource,
+        build: F,
+    ) -> Result<Arc<Gitignore>, DataResolverError>
+    where
+        F: FnOnce(&Path) -> Result<(Gitignore, bool), DataResolverError>,
+    {
+        let version = source.version(path)?;
+        if let Some((cached, rules)) = self.ignores().get(path) {
+            if *cached == version {
+                return Ok(rules.clone());
+            }
+        }
+        let (rules, complete) = build(path)?;
+        let rules = Arc::new(rules);
+        if complete {
+            self.ignores()
+                .insert(path.to_path_buf(), (version, rules.clone()));
+        }
+        Ok(rules)
+    }
+    /// Drops any entries for `path`, or for files beneath it if it is a directory.
+    pub fn invalidate(&self, path: &Path) {
+        self.entries().retain(|cached, _| !cached.starts_with(path));
+        self.ignores().retain(|cached, _| !cached.starts_with(path));
+    }
+    fn entries(&self) -> MutexGuard<'_, HashMap<PathBuf, Entry>> {
+        // entries are only ever replaced whole, so remain consistent after a panic
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    fn ignores(&self) -> MutexGuard<'_, HashMap<PathBuf, (FileVersion, Arc<Gitignore>)>> {
+        self.ignores.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    /// Returns lookup counts since the cache was created.
+    pub fn metrics(&self) -> CacheMetrics {
+        CacheMetrics {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.entries().len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileSystem;
+    use color_eyre::Result;
+    use test_files::TestFiles;
+
+    #[test]
+    fn reuses_documents_until_file_changes() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks.file("a.txt", "1");
+        let path = mocks.path().join("a.txt");
+        let read = |path: &Path| -> Result<Vec<Value>, DataResolverError> {
+            Ok(vec![Value::from(std::fs::read_to_string(path)?)])
+        };
+        let cache = FileCache::new();
+        assert_eq!(
+            cache.documents(&path, &FileSystem, read)?,
+            vec![Value::from("1")]
+        );
+        assert_eq!(
+            cache.documents(&path, &FileSystem, read)?,
+            vec![Value::from("1")]
+        );
+        mocks.file("a.txt", "22");
+        assert_eq!(
+            cache.documents(&path, &FileSystem, read)?,
+            vec![Value::from("22")]
+        );
+        assert!(cache
+            .documents(&mocks.path().join("b.txt"), &FileSystem, read)
+            .is_err());
+        let metrics = cache.metrics();
+        assert_eq!(
+            metrics,
+            CacheMetrics {
+                hits: 1,
+                misses: 2,
+                entries: 1
+            }
+        );
+        assert!((metrics.hit_rate() - 1.0 / 3.0).abs() < f64::EPSILON);
+        Ok(())
+    }
+
+    #[test]
+    fn reuses_ignore_rules_until_file_changes() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks.file(".confqlignore", "a");
+        let path = mocks.path().join(".confqlignore");
+        let builds = std::cell::Cell::new(0);
+        let build = |path: &Path| -> Result<(Gitignore, bool), DataResolverError> {
+            builds.set(builds.get() + 1);
+            let (rules, e) = Gitignore::new(path);
+            Ok((rules, e.is_none()))
+        };
+        let cache = FileCache::new();
+        let ignores = |cache: &FileCache, name: &str| -> Result<bool> {
+            let rules = cache.ignore_rules(&path, &FileSystem, build)?;
+            Ok(rules.matched(mocks.path().join(name), false).is_ignore())
+        };
+        assert!(ignores(&cache, "a")?);
+        assert!(ignores(&cache, "a")?);
+        assert_eq!(builds.get(), 1);
+        mocks.file(".confqlignore", "bb");
+        assert!(!ignores(&cache, "a")?);
+        assert!(ignores(&cache, "bb")?);
+        assert_eq!(builds.get(), 2);
+        cache.invalidate(mocks.path());
+        assert!(ignores(&cache, "bb")?);
+        assert_eq!(builds.get(), 3);
+        Ok(())
+    }
+}
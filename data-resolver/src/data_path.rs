@@ -16,34 +16,245 @@
 //! - merge all data from `a/b/c.yml`
 //! - merge all data from `a/b/c/index.yml`
 //!
+//! Each "file" above may be spelled with any of the extensions recognised by the
+//! registered [DataFormats] (by default `.json`, `.yaml` or `.yml`).  Where more
+//! than one spelling exists at the same position, they are all merged, in the
+//! order given by [DataFormats::extensions()], so that by default `.yml` wins on
+//! conflicting keys.
+//!
+//! A [DataPath] [in a profile](DataPath::in_profile()), e.g. `prod`, also merges the
+//! profile's overlay of each file straight after it, so `a/b.prod.yml` after
+//! `a/b.yml`, and `a/index.prod.yml` after `a/index.yml`.
+//!
+//! When a directory represents a list, its entries are visited in file name order,
+//! unless an `_order.yml` manifest in the directory lists file stems to come first.
+//! A `_defaults.yml` in a list directory, or any directory above it within the data
+//! root, holds defaults for each item, over which the item's own data is merged.
+//! Data holding an `_extends` key, e.g. `_extends: services/api`, is merged over the
+//! data at that address, resolved [from each data root](DataPath::extending()).
+//! Hidden entries (e.g. `.git`), private partials (e.g. `_common.yml`), and entries
+//! matching patterns in a `.confqlignore` file, in gitignore syntax, in the
+//! directory or any above it within the data root, are never visited.
+//! Overlays, i.e. entries with a file stem `<stem>.<profile>` beside an entry
+//! `<stem>`, are not entries of their own in the active profile, or any of those
+//! [known](DataPath::with_profiles()), but are read with entry `<stem>` in their
+//! profile.  Other dotted names, e.g. `web01.example.com.yml`, are entries as usual.
+//!
+//! Files are checked for, listed and read through a [DataSource], by default the
+//! [FileSystem], unless [reading from](DataPath::reading_from()) another.  An
+//! [indexed](DataPath::indexed()) [DataPath] checks for files and lists directories
+//! in a [DirIndex] instead.
+//!
+//! Missing files and keys are expected along the way, and skipped.  Other errors
+//! (e.g. unparseable files) are skipped too, unless the [DataPath] is
+//! [collecting errors](DataPath::collecting_errors()), as a [strict](crate::DataResolver::strict())
+//! resolver's are.
+//!
 //! [DataPath] provides a simple means for performing this process.
-use std::ffi::OsStr;
-use std::fs;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use itertools::Itertools;
+use std::collections::{HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::cache::FileCache;
+use super::dir_index::DirIndex;
+use super::explain::ResolutionStep;
+use super::sources::SOURCES;
+use super::values::{merge_documents, take_sub_value_at_address};
+use super::{DataFormats, DataResolverError, DataSource, FileSystem, Merge};
 
-use super::values::{take_sub_value_at_address, value_from_file};
-use super::DataResolverError;
+/// File stem of data for a directory itself
+const INDEX: &str = "index";
+/// File stem of a manifest pinning the order of a directory's entries
+const ORDER_MANIFEST: &str = "_order";
+/// Default file stem of defaults for the items of lists
+pub const DEFAULTS: &str = "_defaults";
+/// Key giving the data address of data to merge over, e.g. `_extends: services/api`
+pub const EXTENDS: &str = "_extends";
+/// Name of a file of gitignore-style patterns for entries never to visit
+const IGNORE_FILE: &str = ".confqlignore";
 
+#[derive(Clone)]
 enum Level {
     Dir,
     File,
 }
 
+/// Errors collected while resolving data, shared by a [DataPath] and every instance
+/// spawned from it.
+pub type ErrorSink = Arc<Mutex<Vec<DataResolverError>>>;
+
+/// Records a data file, by name, as the source of data read from it.  See
+/// [documents_by_file](DataPath::documents_by_file()).
+pub type Annotate = fn(&mut serde_yaml::Value, &str);
+
 /// Represents a position in the data directory when resolving data.
+#[derive(Clone)]
 pub struct DataPath<'a> {
     level: Level,
     path: PathBuf,
+    root: PathBuf,
     address: &'a [&'a str],
+    formats: Arc<DataFormats>,
+    errors: Option<ErrorSink>,
+    cycles: ErrorSink,
+    sources_root: Option<PathBuf>,
+    data_files: Arc<Mutex<HashMap<PathBuf, Vec<PathBuf>>>>,
+    cache: Option<Arc<FileCache>>,
+    defaults: Arc<str>,
+    defer_defaults: bool,
+    extends: Arc<Vec<String>>,
+    index: Option<Arc<DirIndex>>,
+    layers: Arc<[PathBuf]>,
+    profile: Option<Arc<str>>,
+    profiles: Arc<[String]>,
+    source: Arc<dyn DataSource>,
 }
 
 impl<'a> DataPath<'a> {
+    /// Reads data files through `cache`, rather than parsing them on every read.
+    pub fn caching(mut self, cache: Arc<FileCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+    /// Records [cycles](DataResolverError::ExtendsCycle) of [EXTENDS] in `cycles`, once
+    /// each, whether or not [collecting errors](DataPath::collecting_errors()), as
+    /// data caught in one is never resolved.
+    pub fn collecting_cycles(mut self, cycles: ErrorSink) -> Self {
+        self.cycles = cycles;
+        self
+    }
+    /// Records errors other than missing data in `errors`, rather than skipping them.
+    pub fn collecting_errors(mut self, errors: ErrorSink) -> Self {
+        self.errors = Some(errors);
+        self
+    }
+    /// Records the [sources](crate::sources) of data read for types which track them,
+    /// naming files relative to the current path.
+    pub fn tracking_sources(self) -> Self {
+        let root = self.path.clone();
+        self.tracking_sources_relative_to(root)
+    }
+    /// As [tracking_sources](DataPath::tracking_sources()), but naming files relative
+    /// to `root`, e.g. a directory containing several data roots.
+    pub fn tracking_sources_relative_to<P: Into<PathBuf>>(mut self, root: P) -> Self {
+        self.sources_root = Some(root.into());
+        self
+    }
+    /// Merges the [defaults files](DataPath::with_defaults_file()) of the current path
+    /// and every directory above it within the data root, nearest last, as defaults
+    /// for the items of a list at the current path.  Those of every
+    /// [layer](DataPath::layered()) are merged in turn, as the layers' data is.
+    pub fn defaults(&self) -> serde_yaml::Value {
+        let relative = self.path.strip_prefix(&self.root).unwrap_or(&self.path);
+        let dirs: Vec<PathBuf> = self
+            .layers
+            .iter()
+            .flat_map(|layer| {
+                let path = layer.join(relative);
+                let mut dirs: Vec<PathBuf> = path
+                    .ancestors()
+                    .take_while(|dir| dir.starts_with(layer))
+                    .map(Path::to_path_buf)
+                    .collect();
+                dirs.reverse();
+                dirs
+            })
+            .collect();
+        let mut defaults = serde_yaml::Value::Null;
+        for path in dirs
+            .iter()
+            .flat_map(|dir| self.paths_for(&dir.join(&*self.defaults)))
+            .filter(|path| self.is_file(path))
+        {
+            let documents = self.read_documents(&path).and_then(merge_documents);
+            if let Some(value) = self.ok(documents.map_err(|e| e.at_path(&path, &[]))) {
+                self.ok(defaults
+                    .merge(value)
+                    .map(|_| ())
+                    .map_err(|e| e.at_path(&path, &[])));
+            }
+        }
+        defaults
+    }
+    /// Leaves the items of lists which may be merged with those of other
+    /// [layers](DataPath::layered()), i.e. whose items have
+    /// [identifier fields](crate::ResolveValue::identifier_fields()), unmerged over their
+    /// [defaults](DataPath::defaults()), for [apply_defaults](crate::ResolveValue::apply_defaults())
+    /// once every layer's data is merged, so that defaults never override data given
+    /// for an item in another layer.
+    pub fn deferring_defaults(mut self, defer: bool) -> Self {
+        self.defer_defaults = defer;
+        self
+    }
+    /// Whether [deferring defaults](DataPath::deferring_defaults()).
+    pub fn defers_defaults(&self) -> bool {
+        self.defer_defaults
+    }
+    /// Data address of the current position, relative to the data root, e.g.
+    /// `a/b/c` at path `a/b` and address `["c"]`.
+    fn data_address(&self) -> String {
+        let path = self.path.strip_prefix(&self.root).unwrap_or(&self.path);
+        path.iter()
+            .map(|k| k.to_string_lossy().into_owned())
+            .chain(self.address.iter().map(|k| k.to_string()))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+    /// Spawns a new instance at data address `target` from the root of each
+    /// [layer](DataPath::layered()) in turn, to resolve what data at the current
+    /// position [extends](EXTENDS).  Fails if that data is itself being resolved to
+    /// extend something, naming the cycle of addresses.
+    pub fn extending<'b>(
+        &self,
+        target: &'b [&'b str],
+    ) -> Result<Vec<DataPath<'b>>, DataResolverError> {
+        let mut chain = self.extends.to_vec();
+        let origin = self.data_address();
+        // an extended address is the origin of whatever it extends in turn
+        if chain.last() == Some(&origin) {
+            chain.pop();
+        }
+        for address in [origin, target.join("/")] {
+            if let Some(start) = chain.iter().position(|a| *a == address) {
+                let mut cycle = chain.split_off(start);
+                cycle.push(address);
+                return Err(DataResolverError::ExtendsCycle(cycle));
+            }
+            chain.push(address);
+        }
+        let chain = Arc::new(chain);
+        let layers = self.layers.iter().map(|layer| DataPath {
+            level: Level::Dir,
+            path: layer.clone(),
+            root: layer.clone(),
+            address: target,
+            formats: self.formats.clone(),
+            errors: self.errors.clone(),
+            cycles: self.cycles.clone(),
+            sources_root: self.sources_root.clone(),
+            data_files: self.data_files.clone(),
+            cache: self.cache.clone(),
+            defaults: self.defaults.clone(),
+            defer_defaults: self.defer_defaults,
+            extends: chain.clone(),
+            index: self.index.clone(),
+            layers: self.layers.clone(),
+            profile: self.profile.clone(),
+            profiles: self.profiles.clone(),
+            source: self.source.clone(),
+        });
+        Ok(layers.collect())
+    }
     /// Takes self by value, and steps to the next logical data path (mutating self).  Returns None
     /// if there's nowhere to go.
     pub fn descend(mut self) -> Option<Self> {
         use Level::{Dir, File};
         match &self.level {
             File => {
-                if !self.path.is_dir() {
+                if !self.is_dir(&self.path) {
                     return None;
                 }
                 self.level = Dir;
@@ -58,6 +269,12 @@ impl<'a> DataPath<'a> {
         }
         Some(self)
     }
+    /// Returns the data address remaining beneath the current path, e.g. `["c"]` for
+    /// address `a.b.c` at path `a/b`, which is empty once files of the data itself are
+    /// reached.
+    pub fn address(&self) -> &'a [&'a str] {
+        self.address
+    }
     /// Returns whether or not this instance is exhausted, i.e. when [descend](DataPath::descend()) would
     /// be a no-op
     pub fn done(&self) -> bool {
@@ -67,56 +284,530 @@ impl<'a> DataPath<'a> {
             Dir => self.address.is_empty(),
         }
     }
-    fn file(&self) -> PathBuf {
-        self.path.with_extension("yml")
+    fn files(&self) -> Vec<PathBuf> {
+        self.paths_for(&self.path)
     }
-    /// Returns the current path file stem (i.e. basename without file extension)
+    /// Returns the current path file stem (i.e. basename without file extension).
+    /// Data paths never carry an extension, so any dots are part of the stem.
     pub fn file_stem(&self) -> Option<&OsStr> {
-        self.path.file_stem()
+        self.path.file_name()
+    }
+    /// Lists the data files at and beneath the current position, in merge order.  Lists
+    /// are kept for the rest of the resolution, so that each directory is only listed
+    /// once, however many objects beneath it track their sources.
+    fn data_files(&self) -> Vec<PathBuf> {
+        let listed = self.data_files.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(files) = listed.get(&self.path) {
+            return files.clone();
+        }
+        drop(listed);
+        let mut files: Vec<PathBuf> = self
+            .files()
+            .into_iter()
+            .chain(self.index())
+            .filter(|path| self.is_file(path))
+            .collect();
+        for sub_path in self.sub_paths() {
+            files.extend(sub_path.data_files());
+        }
+        let mut listed = self.data_files.lock().unwrap_or_else(|e| e.into_inner());
+        listed.insert(self.path.clone(), files.clone());
+        files
+    }
+    /// Attributes an error to the data file(s) at the current position, or failing
+    /// that the current path, and the data address being resolved there.
+    pub fn attribute(&self, error: DataResolverError) -> DataResolverError {
+        let path = self
+            .candidates()
+            .into_iter()
+            .find(|path| self.is_file(path))
+            .unwrap_or_else(|| self.path.clone());
+        error.at_path(path, self.address)
+    }
+    /// Consults each data file in resolution order, from the current position down
+    /// to every file beneath the addressed data, recording a [ResolutionStep] for
+    /// each.  Contributions are merged as plain data, by [Merge::merge()], so can
+    /// differ from what a particular type resolves, e.g. where lists are concerned.
+    pub fn explain(self) -> Vec<ResolutionStep> {
+        let mut steps = vec![];
+        self.explain_onto(&mut serde_yaml::Value::Null, &mut steps);
+        steps
+    }
+    /// As [explain](DataPath::explain()), but merging contributions over `merged`,
+    /// e.g. the data under an earlier data root, and appending to `steps`.
+    pub fn explain_onto(self, merged: &mut serde_yaml::Value, steps: &mut Vec<ResolutionStep>) {
+        self.explain_into(&[], merged, steps);
+    }
+    fn explain_into(
+        self,
+        position: &[String],
+        merged: &mut serde_yaml::Value,
+        steps: &mut Vec<ResolutionStep>,
+    ) {
+        let mut data_path = Some(self);
+        while let Some(current) = data_path {
+            for file in current.candidates() {
+                steps.push(current.explain_file(file, position, merged));
+            }
+            if current.done() {
+                for sub_path in current.sub_paths() {
+                    let mut position = position.to_vec();
+                    position.extend(sub_path.file_stem().map(|s| s.to_string_lossy().into()));
+                    sub_path.explain_into(&position, merged, steps);
+                }
+                break;
+            }
+            data_path = current.descend();
+        }
+    }
+    /// Whether there is any data at the addressed position, i.e. a value at its
+    /// address in some file on the way down, or a directory for it.
+    pub fn exists(self) -> bool {
+        let mut data_path = Some(self);
+        while let Some(current) = data_path {
+            let documents = current.documents().unwrap_or_default();
+            if documents.iter().any(|document| !document.is_null()) {
+                return true;
+            }
+            if current.done() {
+                return current.is_dir(&current.path);
+            }
+            data_path = current.descend();
+        }
+        false
+    }
+    fn explain_file(
+        &self,
+        file: PathBuf,
+        position: &[String],
+        merged: &mut serde_yaml::Value,
+    ) -> ResolutionStep {
+        use serde_yaml::{Mapping, Value};
+        let exists = self.is_file(&file);
+        let mut contributed = None;
+        let mut error = None;
+        if exists {
+            match self
+                .get_file_documents(&file, None)
+                .and_then(merge_documents)
+            {
+                Ok(value) => {
+                    let mergee = position.iter().rev().fold(value.clone(), |v, key| {
+                        let mut mapping = Mapping::new();
+                        mapping.insert(Value::from(key.as_str()), v);
+                        Value::Mapping(mapping)
+                    });
+                    if let Err(e) = merged.merge(mergee) {
+                        error = Some(e.to_string());
+                    }
+                    contributed = Some(value);
+                }
+                Err(e) if e.is_missing_data() => {}
+                Err(e) => error = Some(e.to_string()),
+            }
+        }
+        ResolutionStep {
+            file,
+            exists,
+            address: self.address.iter().map(|k| k.to_string()).collect(),
+            position: position.to_vec(),
+            contributed,
+            error,
+            merged: merged.clone(),
+        }
     }
-    fn get_value(&self, path: &Path) -> Result<serde_yaml::Value, DataResolverError> {
-        let mut value = value_from_file(path)?;
-        take_sub_value_at_address(&mut value, self.address)
+    fn get_file_documents(
+        &self,
+        path: &Path,
+        annotate: Option<Annotate>,
+    ) -> Result<Vec<serde_yaml::Value>, DataResolverError> {
+        let (mut documents, mut errors): (Vec<_>, Vec<_>) = self
+            .read_documents(path)
+            .map_err(|e| e.at_path(path, self.address))?
+            .into_iter()
+            .map(|mut document| take_sub_value_at_address(&mut document, self.address))
+            .partition_result();
+        if let (Some(annotate), Some(source)) = (annotate, self.source_name(path)) {
+            for document in documents.iter_mut() {
+                annotate(document, &source);
+            }
+        }
+        match documents.is_empty() && !errors.is_empty() {
+            true => Err(errors.remove(0).at_path(path, self.address)),
+            false => Ok(documents),
+        }
+    }
+    /// Collects documents from each of the candidate paths which yield any, in order.
+    /// If none do, the first error other than missing data is returned, or failing
+    /// that the first error.  Any other errors are [recorded](DataPath::ok()).
+    fn get_documents(
+        &self,
+        paths: Vec<PathBuf>,
+        annotate: Option<Annotate>,
+    ) -> Result<Vec<Vec<serde_yaml::Value>>, DataResolverError> {
+        let mut documents: Option<Vec<Vec<serde_yaml::Value>>> = None;
+        let mut errors = vec![];
+        for path in paths {
+            match self.get_file_documents(&path, annotate) {
+                Ok(found) => documents.get_or_insert_with(Vec::new).push(found),
+                Err(e) => errors.push(e),
+            }
+        }
+        let result = match documents {
+            Some(documents) => Ok(documents),
+            None => {
+                let first = errors.iter().position(|e| !e.is_missing_data());
+                Err(errors.remove(first.unwrap_or(0)))
+            }
+        };
+        for e in errors {
+            self.ok::<()>(Err(e));
+        }
+        result
     }
-    fn index(&self) -> PathBuf {
-        self.path.join("index.yml")
+    /// Checks for files and lists directories in `index`, rather than on the
+    /// filesystem.
+    pub fn indexed(mut self, index: Arc<DirIndex>) -> Self {
+        self.index = Some(index);
+        self
+    }
+    fn is_dir(&self, path: &Path) -> bool {
+        match &self.index {
+            Some(index) => index.is_dir(path),
+            None => self.source.is_dir(path),
+        }
+    }
+    fn is_file(&self, path: &Path) -> bool {
+        match &self.index {
+            Some(index) => index.is_file(path),
+            None => self.source.is_file(path),
+        }
+    }
+    fn index(&self) -> Vec<PathBuf> {
+        self.paths_for(&self.path.join(INDEX))
+    }
+    /// Merges the overlay files of `profile`, e.g. `a.prod.yml`, after each data file.
+    pub fn in_profile<S: Into<Arc<str>>>(mut self, profile: S) -> Self {
+        self.profile = Some(profile.into());
+        self
+    }
+    /// Treats the current data root as one of several `layers`, e.g. of a
+    /// [DataResolver](crate::DataResolver) with several data roots, whose data is
+    /// merged in turn.  See [defaults](DataPath::defaults()).
+    pub fn layered<L: Into<Arc<[PathBuf]>>>(mut self, layers: L) -> Self {
+        self.layers = layers.into();
+        self
     }
     /// Spawns a new instance with a given path suffix appended, and same data address.
     pub fn join<P: AsRef<Path>>(&self, tail: P) -> Self {
         Self {
             level: Level::File,
             path: self.path.join(tail),
+            root: self.root.clone(),
             address: self.address,
+            formats: self.formats.clone(),
+            errors: self.errors.clone(),
+            cycles: self.cycles.clone(),
+            sources_root: self.sources_root.clone(),
+            data_files: self.data_files.clone(),
+            cache: self.cache.clone(),
+            defaults: self.defaults.clone(),
+            defer_defaults: self.defer_defaults,
+            extends: self.extends.clone(),
+            index: self.index.clone(),
+            layers: self.layers.clone(),
+            profile: self.profile.clone(),
+            profiles: self.profiles.clone(),
+            source: self.source.clone(),
+        }
+    }
+    /// If [tracking sources](DataPath::tracking_sources()), records the data files at
+    /// and beneath each of the given fields of an object at the current position, e.g.
+    /// `a/b.yml` for field `b` at `a`, among the sources of those fields in `value`.
+    pub fn merge_sources(
+        &self,
+        value: &mut serde_yaml::Value,
+        fields: &[&str],
+    ) -> Result<(), DataResolverError> {
+        use serde_yaml::{Mapping, Value};
+        if self.sources_root.is_none() {
+            return Ok(());
+        }
+        let mut sources = Mapping::new();
+        for field in fields {
+            let files: Vec<Value> = self
+                .join(field)
+                .data_files()
+                .iter()
+                .filter_map(|file| self.source_name(file))
+                .map(Value::from)
+                .collect();
+            if !files.is_empty() {
+                sources.insert(Value::from(*field), Value::Sequence(files));
+            }
+        }
+        if !sources.is_empty() {
+            value.merge_at(SOURCES, Value::Mapping(sources))?;
         }
+        Ok(())
     }
-    /// Creates a new instance from a path and data address.
+    /// Creates a new instance from a path and data address, recognising the
+    /// [default](DataFormats::default()) data formats.
     pub fn new<P: Into<PathBuf>>(path: P, address: &'a [&'a str]) -> Self {
+        Self::with_formats(path, address, Arc::new(DataFormats::default()))
+    }
+    /// Creates a new instance from a path and data address, recognising the
+    /// given data formats.
+    pub fn with_formats<P: Into<PathBuf>>(
+        path: P,
+        address: &'a [&'a str],
+        formats: Arc<DataFormats>,
+    ) -> Self {
+        let path = path.into();
         Self {
             address,
+            formats,
+            errors: None,
+            cycles: Default::default(),
             level: Level::Dir,
-            path: path.into(),
+            layers: vec![path.clone()].into(),
+            root: path.clone(),
+            path,
+            sources_root: None,
+            data_files: Default::default(),
+            cache: None,
+            defaults: DEFAULTS.into(),
+            defer_defaults: false,
+            extends: Default::default(),
+            index: None,
+            profile: None,
+            profiles: Arc::new([]),
+            source: Arc::new(FileSystem),
+        }
+    }
+    /// Converts a result into an [Option], as [Result::ok()] does, first recording any
+    /// error other than missing data if [collecting errors](DataPath::collecting_errors()).
+    /// [Cycles](DataPath::collecting_cycles()) of [EXTENDS] are always recorded.
+    pub fn ok<T>(&self, result: Result<T, DataResolverError>) -> Option<T> {
+        match (result, &self.errors) {
+            (Ok(v), _) => Some(v),
+            (Err(e), _) if e.extends_cycle().is_some() => {
+                if let Ok(mut cycles) = self.cycles.lock() {
+                    if !cycles.iter().any(|c| c.is_same_cycle(&e)) {
+                        cycles.push(e);
+                    }
+                }
+                None
+            }
+            (Err(e), Some(errors)) if !e.is_missing_data() => {
+                if let Ok(mut errors) = errors.lock() {
+                    errors.push(e);
+                }
+                None
+            }
+            (Err(_), _) => None,
+        }
+    }
+    /// Candidate data files for `path` in each recognised format, followed by those of
+    /// the active profile's overlay, if any.
+    fn paths_for(&self, path: &Path) -> Vec<PathBuf> {
+        let mut paths = self.formats.paths_for(path);
+        if let Some(profile) = &self.profile {
+            paths.extend(self.formats.extensions().map(|ext| {
+                let mut overlay = path.as_os_str().to_owned();
+                overlay.push(format!(".{}.{}", profile, ext));
+                PathBuf::from(overlay)
+            }));
+        }
+        paths
+    }
+    /// Whether `stem` is an overlay of another of `stems` in the active profile or a
+    /// [known](DataPath::with_profiles()) one, e.g. `a.prod` beside `a` in profile
+    /// `prod`.  Other dotted names are items of their own.
+    fn is_overlay(&self, stem: &OsStr, stems: &HashSet<OsString>) -> bool {
+        match stem.to_str().and_then(|s| s.rsplit_once('.')) {
+            Some((base, profile)) => {
+                (self.profile.as_deref() == Some(profile)
+                    || self.profiles.iter().any(|p| p == profile))
+                    && stems.contains(OsStr::new(base))
+            }
+            None => false,
+        }
+    }
+    /// Reads the [IGNORE_FILE]s applying to entries at the current path, from the data
+    /// root down, each from the cache if there is one.
+    fn ignore_rules(&self) -> Vec<Arc<Gitignore>> {
+        let dirs: Vec<&Path> = self
+            .path
+            .ancestors()
+            .take_while(|dir| dir.starts_with(&self.root))
+            .collect();
+        dirs.into_iter()
+            .rev()
+            .filter_map(|dir| {
+                let path = dir.join(IGNORE_FILE);
+                if !self.is_file(&path) {
+                    return None;
+                }
+                let attribute = |e: DataResolverError| e.at_path(&path, &[]);
+                // bad patterns are reported and skipped, leaving the rest of the file
+                let build = |path: &Path| -> Result<(Gitignore, bool), DataResolverError> {
+                    let content = self.source.read_file(path)?;
+                    let mut builder = GitignoreBuilder::new(dir);
+                    let mut complete = true;
+                    for line in content.lines() {
+                        let added = builder
+                            .add_line(None, line)
+                            .map_err(|e| attribute(e.into()));
+                        complete &= self.ok(added).is_some();
+                    }
+                    Ok((builder.build()?, complete))
+                };
+                let rules = match &self.cache {
+                    Some(cache) => cache.ignore_rules(&path, self.source.as_ref(), build),
+                    None => build(&path).map(|(rules, _)| Arc::new(rules)),
+                };
+                self.ok(rules.map_err(attribute))
+            })
+            .collect()
+    }
+    /// Whether to skip entry `name` of the current path, as hidden, private, or
+    /// matched by one of `rules`, the deepest matching rule taking precedence.
+    fn ignores(&self, name: &OsStr, rules: &[Arc<Gitignore>]) -> bool {
+        if name.to_string_lossy().starts_with(&['.', '_'][..]) {
+            return true;
+        }
+        let path = self.path.join(name);
+        let is_dir = self.is_dir(&path);
+        rules
+            .iter()
+            .rev()
+            .map(|rules| rules.matched_path_or_any_parents(&path, is_dir))
+            .find(|matched| !matched.is_none())
+            .is_some_and(|matched| matched.is_ignore())
+    }
+    /// Reads file stems listed in an `_order` manifest at the current path, if any.
+    fn order_manifest(&self) -> Vec<OsString> {
+        self.formats
+            .paths_for(&self.path.join(ORDER_MANIFEST))
+            .iter()
+            .filter_map(|path| self.ok(self.read_documents(path).map_err(|e| e.at_path(path, &[]))))
+            .flatten()
+            .flat_map(|document| match document {
+                serde_yaml::Value::Sequence(stems) => stems,
+                _ => vec![],
+            })
+            .filter_map(|stem| stem.as_str().map(OsString::from))
+            .collect()
+    }
+    /// Lists the names of entries at the current path, if it is a directory.
+    fn list_dir(&self) -> Option<Vec<OsString>> {
+        match &self.index {
+            Some(index) => index.list(&self.path),
+            None => self.source.list_dir(&self.path),
+        }
+    }
+    /// Reads the documents of a data file, from the cache if there is one.  An
+    /// [indexed](DataPath::indexed()) instance only reads files in its index.
+    fn read_documents(&self, path: &Path) -> Result<Vec<serde_yaml::Value>, DataResolverError> {
+        if self.index.is_some() && !self.is_file(path) {
+            return Err(std::io::Error::from(std::io::ErrorKind::NotFound).into());
+        }
+        let read = |path: &Path| self.formats.documents_from_file(path, self.source.as_ref());
+        match &self.cache {
+            Some(cache) => cache.documents(path, self.source.as_ref(), read),
+            None => read(path),
         }
     }
+    /// Reads defaults for list items from files with stem `stem` rather than
+    /// [DEFAULTS].  See [defaults](DataPath::defaults()).
+    pub fn with_defaults_file<S: Into<Arc<str>>>(mut self, stem: S) -> Self {
+        self.defaults = stem.into();
+        self
+    }
+    /// Treats entries suffixed with any of `profiles`, e.g. `a.staging.yml` beside
+    /// `a.yml`, as overlays rather than entries of their own, whether or not in that
+    /// profile.  See [sub_paths](DataPath::sub_paths()).
+    pub fn with_profiles<P: Into<Arc<[String]>>>(mut self, profiles: P) -> Self {
+        self.profiles = profiles.into();
+        self
+    }
+    /// Checks for, lists and reads files in `source`, rather than the filesystem.
+    pub fn reading_from(mut self, source: Arc<dyn DataSource>) -> Self {
+        self.source = source;
+        self
+    }
+    /// Names a data file as a source, relative to the root of
+    /// [source tracking](DataPath::tracking_sources()), if enabled.
+    fn source_name(&self, path: &Path) -> Option<String> {
+        let root = self.sources_root.as_ref()?;
+        Some(
+            path.strip_prefix(root)
+                .unwrap_or(path)
+                .display()
+                .to_string(),
+        )
+    }
     /// Creates a vector of new instances, one for each file/directory at the current path.
+    /// Data files sharing a stem (e.g. `a.yml` and `a.json`) yield a single instance, and
+    /// `index` files, holding data for the current path itself, yield none.
+    ///
+    /// Profile overlays beside the stem they overlay yield no instance of their own,
+    /// in the active profile or any [known](DataPath::with_profiles()) one.
+    ///
+    /// Instances are sorted by file stem, except that any stems listed in an `_order`
+    /// manifest come first, in the listed order.
     pub fn sub_paths(&self) -> Vec<Self> {
-        fs::read_dir(&self.path).map_or_else(
-            |_| vec![],
-            |reader| {
-                reader
-                    .filter_map(|dir_entry| dir_entry.ok())
-                    .map(|dir_entry| dir_entry.file_name())
-                    .map(|p| self.join(p))
-                    .collect()
-            },
-        )
+        let order = self.order_manifest();
+        self.list_dir().map_or_else(Vec::new, |names| {
+            let rules = self.ignore_rules();
+            let stems: HashSet<OsString> = names
+                .into_iter()
+                .filter(|name| !self.ignores(name, &rules))
+                .map(|name| self.formats.strip_extension(name))
+                .collect();
+            stems
+                .iter()
+                .filter(|stem| !self.is_overlay(stem, &stems))
+                .filter(|&p| p != INDEX && p != ORDER_MANIFEST && *p != *self.defaults)
+                .cloned()
+                .sorted_by_key(|p| {
+                    let position = order.iter().position(|o| o == p);
+                    (position.unwrap_or(order.len()), p.clone())
+                })
+                .map(|p| self.join(p))
+                .collect()
+        })
     }
-    /// Tries to convert the current position to a [serde_yaml::Value].
-    pub fn value(&self) -> Result<serde_yaml::Value, DataResolverError> {
+    /// Tries to read the [serde_yaml::Value] of every document at the current position.
+    /// Files holding several documents (e.g. `---` separated yaml) yield one per document.
+    pub fn documents(&self) -> Result<Vec<serde_yaml::Value>, DataResolverError> {
+        let documents = self.get_documents(self.candidates(), None)?;
+        Ok(documents.into_iter().flatten().collect())
+    }
+    /// As [documents](DataPath::documents()), but grouped by the file they were read
+    /// from, e.g. `a.json` then `a.yml`, so that spellings of one file can be merged
+    /// before documents of each are taken as list items.  If [tracking sources](DataPath::tracking_sources()),
+    /// each document is passed to `annotate` with the name of its file, e.g. by
+    /// [annotate_sources](crate::ResolveValue::annotate_sources()).
+    pub fn documents_by_file(
+        &self,
+        annotate: Annotate,
+    ) -> Result<Vec<Vec<serde_yaml::Value>>, DataResolverError> {
+        self.get_documents(self.candidates(), Some(annotate))
+    }
+    /// The data files which may hold data for the current position.
+    fn candidates(&self) -> Vec<PathBuf> {
         match &self.level {
-            Level::Dir => self.get_value(&self.index()),
-            Level::File => self.get_value(&self.file()),
+            Level::Dir => self.index(),
+            Level::File => self.files(),
         }
     }
+    /// Tries to convert the current position to a [serde_yaml::Value], merging
+    /// [documents](DataPath::documents()) in order.
+    pub fn value(&self) -> Result<serde_yaml::Value, DataResolverError> {
+        merge_documents(self.documents()?)
+    }
 }
 
 #[cfg(test)]
@@ -152,6 +843,19 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn resolves_dotted_file_name() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks.file("hosts/web01.example.com.yml", "port: 80");
+        let data_path = mocks
+            .data_path(&["port"])
+            .join("hosts")
+            .join("web01.example.com");
+        assert_eq!(data_path.file_stem(), Some(OsStr::new("web01.example.com")));
+        assert_eq!(data_path.value()?, yaml! {"80"});
+        Ok(())
+    }
+
     #[test]
     fn resolves_num_deeper() -> Result<()> {
         let mocks = TestFiles::new();
@@ -186,4 +890,259 @@ mod tests {
         assert_eq!(v, yaml! {"[4, 5, 6]"});
         Ok(())
     }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn resolves_num_deeper_from_json() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks.file("a/index.json", r#"{"b": {"c": 3}}"#);
+        let v = mocks
+            .data_path(&["a", "b", "c"])
+            .descend()
+            .and_then(DataPath::descend)
+            .unwrap()
+            .value()?;
+        assert_eq!(v, yaml! {"3"});
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn merges_same_stem_files_with_yml_taking_precedence() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks
+            .file("a.json", r#"{"json": 1, "yaml": 1, "yml": 1}"#)
+            .file(
+                "a.yaml",
+                indoc! {"
+                    ---
+                    yaml: 2
+                    yml: 2
+                "},
+            )
+            .file(
+                "a.yml",
+                indoc! {"
+                    ---
+                    yml: 3
+                "},
+            );
+        let v = mocks.data_path(&["a"]).descend().unwrap().value()?;
+        assert_eq!(
+            v,
+            yaml! {"
+                ---
+                json: 1
+                yaml: 2
+                yml: 3
+            "}
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_documents_at_address() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks.file(
+            "index.yml",
+            indoc! {"
+	            ---
+	            a: 1
+	            ---
+	            b: 2
+	            ---
+	            a: 3
+	        "},
+        );
+        let data_path = mocks.data_path(&["a"]);
+        assert_eq!(data_path.documents()?, vec![yaml! {"1"}, yaml! {"3"}]);
+        assert_eq!(data_path.value()?, yaml! {"3"});
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn explains_resolution() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks
+            .file("index.yml", "a: {b: {c: 1, d: 1}}")
+            .file("a.json", r#"{"b": {"c": 2}}"#)
+            .file("a/b/e.yml", "3");
+        let steps = mocks.data_path(&["a", "b"]).explain();
+        // index, a, a/index, a/b, a/b/index and a/b/e, in each extension
+        assert_eq!(steps.len(), 6 * DataFormats::default().extensions().count());
+        let found: Vec<_> = steps
+            .iter()
+            .filter(|s| s.exists)
+            .map(|s| {
+                let file = s.file.strip_prefix(mocks.path()).unwrap();
+                (
+                    file.to_str().unwrap(),
+                    s.address.join("."),
+                    s.position.join("."),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("index.yml", "a.b".to_string(), "".to_string()),
+                ("a.json", "b".to_string(), "".to_string()),
+                ("a/b/e.yml", "".to_string(), "e".to_string()),
+            ]
+        );
+        let merged: Vec<_> = steps
+            .iter()
+            .filter(|s| s.exists)
+            .map(|s| s.merged.clone())
+            .collect();
+        assert_eq!(
+            merged,
+            vec![
+                yaml! {"{c: 1, d: 1}"},
+                yaml! {"{c: 2, d: 1}"},
+                yaml! {"{c: 2, d: 1, e: 3}"},
+            ]
+        );
+        assert!(steps.last().unwrap().to_string().ends_with(indoc! {"
+            a/b/e.yml
+                address: (root)
+                position: e
+                contributed:
+                    3
+                merged:
+                    c: 2
+                    d: 1
+                    e: 3"
+        }));
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn sub_paths_unique_by_stem() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks
+            .file("a.json", "1")
+            .file("a.yml", "1")
+            .file("b.yaml", "2")
+            .file("c/index.yml", "3")
+            .file("index.yml", "4");
+        let stems: Vec<_> = mocks
+            .data_path(&[])
+            .sub_paths()
+            .iter()
+            .filter_map(|dp| dp.file_stem().map(OsStr::to_owned))
+            .collect();
+        assert_eq!(stems, vec!["a", "b", "c"]);
+        Ok(())
+    }
+
+    #[test]
+    fn sub_paths_ordered_by_manifest() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks
+            .file("a.yml", "1")
+            .file("b.yml", "2")
+            .file("c.yml", "3")
+            .file("d/index.yml", "4")
+            .file(
+                "_order.yml",
+                indoc! {"
+                    ---
+                    - d
+                    - b
+                "},
+            );
+        let stems: Vec<_> = mocks
+            .data_path(&[])
+            .sub_paths()
+            .iter()
+            .filter_map(|dp| dp.file_stem().map(OsStr::to_owned))
+            .collect();
+        assert_eq!(stems, vec!["d", "b", "a", "c"]);
+        Ok(())
+    }
+
+    #[test]
+    fn merges_profile_overlays() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks
+            .file("index.yml", "a: {b: 1, c: 1}")
+            .file("index.prod.yml", "a: {b: 2}")
+            .file("a.yml", "c: 2")
+            .file("a.staging.yml", "c: 3");
+        let v = mocks.data_path(&["a"]).value()?;
+        assert_eq!(v, yaml! {"{b: 1, c: 1}"});
+        let v = mocks.data_path(&["a"]).in_profile("prod").value()?;
+        assert_eq!(v, yaml! {"{b: 2, c: 1}"});
+        let v = mocks.data_path(&["a"]).in_profile("staging").value()?;
+        assert_eq!(v, yaml! {"{b: 1, c: 1}"});
+        let data_path = mocks
+            .data_path(&["a"])
+            .in_profile("staging")
+            .descend()
+            .unwrap();
+        assert_eq!(data_path.value()?, yaml! {"{c: 3}"});
+        Ok(())
+    }
+
+    #[test]
+    fn sub_paths_skip_profile_overlays() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks
+            .file("a.yml", "1")
+            .file("a.prod.yml", "2")
+            .file("b.staging.yml", "3");
+        let stems = |data_path: DataPath| -> Vec<_> {
+            data_path
+                .sub_paths()
+                .iter()
+                .filter_map(|dp| dp.file_stem().map(OsStr::to_owned))
+                .collect()
+        };
+        assert_eq!(
+            stems(mocks.data_path(&[])),
+            vec!["a", "a.prod", "b.staging"]
+        );
+        assert_eq!(
+            stems(mocks.data_path(&[]).in_profile("prod")),
+            vec!["a", "b.staging"]
+        );
+        assert_eq!(
+            stems(mocks.data_path(&[]).in_profile("staging")),
+            vec!["a", "a.prod", "b.staging"]
+        );
+        let profiles = vec!["prod".to_owned(), "staging".to_owned()];
+        assert_eq!(
+            stems(mocks.data_path(&[]).with_profiles(profiles)),
+            vec!["a", "b.staging"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sub_paths_skip_ignored_entries() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks
+            .file("a.yml", "1")
+            .file(".b.yml", "2")
+            .file("_c.yml", "3")
+            .file("d/index.yml", "4")
+            .file("e.yml", "5")
+            .file("f/e.yml", "6")
+            .file("f/g.yml", "7")
+            .file(".confqlignore", "d/\ne.yml\n")
+            .file("f/.confqlignore", "!e.yml\ng.*\n");
+        let stems = |data_path: DataPath| -> Vec<_> {
+            data_path
+                .sub_paths()
+                .iter()
+                .filter_map(|dp| dp.file_stem().map(OsStr::to_owned))
+                .collect()
+        };
+        assert_eq!(stems(mocks.data_path(&[])), vec!["a", "f"]);
+        assert_eq!(stems(mocks.data_path(&[]).join("f")), vec!["e"]);
+        Ok(())
+    }
 }
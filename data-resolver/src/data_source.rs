@@ -0,0 +1,188 @@
+//! Data sources.
+//!
+//! A [DataPath](crate::DataPath) finds data files by checking for files and
+//! directories, listing directories and reading files, all through a [DataSource].
+//! By default that is the [FileSystem], but a [DataResolver](crate::DataResolver)
+//! may be given [another](crate::DataResolver::with_source()), e.g. a
+//! [MemorySource] for tests, without any change to how data is merged.
+//!
+//! ```
+//! use confql_data_resolver::{DataResolver, MemorySource};
+//! use std::path::PathBuf;
+//!
+//! let source = MemorySource::new();
+//! source.file("/data/a.yml", "b: 1");
+//! let resolver = DataResolver::from(PathBuf::from("/data")).with_source(source);
+//! assert_eq!(resolver.get::<i32>(&["a", "b"]).unwrap(), 1);
+//! ```
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{RwLock, RwLockReadGuard};
+use std::time::SystemTime;
+
+/// Identifies a version of a file's content, so that data parsed from it can be
+/// reused until it changes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FileVersion {
+    /// When the file was last modified, if known
+    pub modified: Option<SystemTime>,
+    /// Size of the file's content, in bytes
+    pub len: u64,
+}
+
+/// A tree of files and directories from which data is read.
+pub trait DataSource: Send + Sync {
+    /// Resolves links in `path`, so that a directory reached by several paths is
+    /// recognised as the same.  The default returns `path` as is.
+    fn canonicalize(&self, path: &Path) -> PathBuf {
+        path.to_path_buf()
+    }
+    /// Whether `path` is a directory.
+    fn is_dir(&self, path: &Path) -> bool;
+    /// Whether `path` is a file.
+    fn is_file(&self, path: &Path) -> bool;
+    /// Names of the entries of directory `path`, or None if it is not a directory.
+    fn list_dir(&self, path: &Path) -> Option<Vec<OsString>>;
+    /// Reads the content of file `path`.  Fails with [io::ErrorKind::NotFound] if
+    /// there is no such file.
+    fn read_file(&self, path: &Path) -> io::Result<String>;
+    /// Returns the current [FileVersion] of file `path`.  Fails with
+    /// [io::ErrorKind::NotFound] if there is no such file.
+    fn version(&self, path: &Path) -> io::Result<FileVersion>;
+}
+
+/// The local filesystem, following symlinks.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FileSystem;
+
+impl DataSource for FileSystem {
+    fn canonicalize(&self, path: &Path) -> PathBuf {
+        fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+    }
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+    fn list_dir(&self, path: &Path) -> Option<Vec<OsString>> {
+        fs::read_dir(path).ok().map(|reader| {
+            reader
+                .filter_map(|dir_entry| dir_entry.ok())
+                .map(|dir_entry| dir_entry.file_name())
+                .collect()
+        })
+    }
+    fn read_file(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+    fn version(&self, path: &Path) -> io::Result<FileVersion> {
+        let metadata = fs::metadata(path)?;
+        Ok(FileVersion {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
+/// Files held in memory, keyed by path.  Directories are implied by the files
+/// beneath them.
+#[derive(Debug, Default)]
+pub struct MemorySource {
+    files: RwLock<BTreeMap<PathBuf, (String, SystemTime)>>,
+}
+
+impl MemorySource {
+    /// Creates an instance with no files.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Adds or replaces a file.
+    pub fn file<P: Into<PathBuf>, S: Into<String>>(&self, path: P, content: S) -> &Self {
+        self.files
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(path.into(), (content.into(), SystemTime::now()));
+        self
+    }
+    fn files(&self) -> RwLockReadGuard<'_, BTreeMap<PathBuf, (String, SystemTime)>> {
+        self.files.read().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl DataSource for MemorySource {
+    fn is_dir(&self, path: &Path) -> bool {
+        self.files()
+            .keys()
+            .any(|file| file != path && file.starts_with(path))
+    }
+    fn is_file(&self, path: &Path) -> bool {
+        self.files().contains_key(path)
+    }
+    fn list_dir(&self, path: &Path) -> Option<Vec<OsString>> {
+        let mut names: Vec<OsString> = self
+            .files()
+            .keys()
+            .filter_map(|file| file.strip_prefix(path).ok()?.iter().next())
+            .map(OsString::from)
+            .collect();
+        names.dedup();
+        match names.is_empty() {
+            true => None,
+            false => Some(names),
+        }
+    }
+    fn read_file(&self, path: &Path) -> io::Result<String> {
+        match self.files().get(path) {
+            Some((content, _)) => Ok(content.clone()),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+    fn version(&self, path: &Path) -> io::Result<FileVersion> {
+        match self.files().get(path) {
+            Some((content, modified)) => Ok(FileVersion {
+                modified: Some(*modified),
+                len: content.len() as u64,
+            }),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_source_implies_directories() {
+        let source = MemorySource::new();
+        source
+            .file("/data/index.yml", "---")
+            .file("/data/a/b.yml", "c: 1")
+            .file("/data/a/d/e.yml", "---");
+        assert!(source.is_dir(Path::new("/data")));
+        assert!(source.is_dir(Path::new("/data/a")));
+        assert!(!source.is_dir(Path::new("/data/a/b.yml")));
+        assert!(source.is_file(Path::new("/data/a/b.yml")));
+        assert!(!source.is_file(Path::new("/data/a")));
+        assert_eq!(
+            source.list_dir(Path::new("/data/a")),
+            Some(vec![OsString::from("b.yml"), OsString::from("d")])
+        );
+        assert_eq!(source.list_dir(Path::new("/data/c")), None);
+        assert_eq!(
+            source.read_file(Path::new("/data/a/b.yml")).unwrap(),
+            "c: 1"
+        );
+        assert_eq!(
+            source
+                .read_file(Path::new("/data/c.yml"))
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+}
@@ -0,0 +1,144 @@
+//! In-memory directory indexing.
+//!
+//! Resolving an address probes many candidate files which don't exist (see
+//! [DataPath](crate::DataPath)), and lists directories along the way.  On a slow
+//! filesystem, an [indexed](crate::DataResolver::indexed()) resolver avoids those
+//! probes by walking the data root once, through its [DataSource], into a
+//! [DirIndex], and answering from it whether a path is a file or directory, and
+//! what a directory contains.  Only files known to exist are ever opened.
+//!
+//! The index does not notice changes to the data root by itself, so should be
+//! [refreshed](crate::DataResolver::refresh_index()) after files are added or
+//! removed.  Changes to the content of existing files need no refresh.
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+
+use super::{DataSource, FileSystem};
+
+#[derive(Default)]
+struct Tree {
+    dirs: HashMap<PathBuf, Vec<OsString>>,
+    files: HashSet<PathBuf>,
+}
+
+impl Tree {
+    fn scan(roots: &[PathBuf], source: &dyn DataSource) -> Self {
+        let mut tree = Self::default();
+        let mut visited = HashSet::new();
+        for root in roots {
+            tree.scan_dir(root.clone(), source, &mut visited);
+        }
+        tree
+    }
+    fn scan_dir(&mut self, dir: PathBuf, source: &dyn DataSource, visited: &mut HashSet<PathBuf>) {
+        // guard against symlink cycles
+        if !visited.insert(source.canonicalize(&dir)) {
+            return;
+        }
+        let mut names = vec![];
+        for name in source.list_dir(&dir).unwrap_or_default() {
+            let path = dir.join(&name);
+            if source.is_dir(&path) {
+                // hidden directories, e.g. `.git`, are never visited
+                if name.to_string_lossy().starts_with('.') {
+                    continue;
+                }
+                self.scan_dir(path, source, visited);
+            } else if source.is_file(&path) {
+                self.files.insert(path);
+            } else {
+                continue;
+            }
+            names.push(name);
+        }
+        self.dirs.insert(dir, names);
+    }
+}
+
+/// Snapshot of the files and directories beneath one or more data roots.
+pub struct DirIndex {
+    roots: Vec<PathBuf>,
+    source: Arc<dyn DataSource>,
+    tree: RwLock<Tree>,
+}
+
+impl DirIndex {
+    /// Whether `path` was a directory when last scanned.
+    pub fn is_dir(&self, path: &Path) -> bool {
+        self.tree().dirs.contains_key(path)
+    }
+    /// Whether `path` was a file when last scanned.
+    pub fn is_file(&self, path: &Path) -> bool {
+        self.tree().files.contains(path)
+    }
+    /// Names of the entries of directory `path` when last scanned, or None if it
+    /// was not a directory.
+    pub fn list(&self, path: &Path) -> Option<Vec<OsString>> {
+        self.tree().dirs.get(path).cloned()
+    }
+    /// Walks the directory tree under each root again, replacing the index.
+    pub fn refresh(&self) {
+        let tree = Tree::scan(&self.roots, self.source.as_ref());
+        *self.tree.write().unwrap_or_else(|e| e.into_inner()) = tree;
+    }
+    /// Walks the directory tree under `root` to create a new index.  Anything which
+    /// can't be read is left out.
+    pub fn scan<P: Into<PathBuf>>(root: P) -> Self {
+        Self::scan_source(root, Arc::new(FileSystem))
+    }
+    /// As [scan](DirIndex::scan()), but walking `root` in `source` rather than the
+    /// filesystem.
+    pub fn scan_source<P: Into<PathBuf>>(root: P, source: Arc<dyn DataSource>) -> Self {
+        Self::scan_roots(vec![root.into()], source)
+    }
+    /// As [scan_source](DirIndex::scan_source()), but walking each of several data
+    /// roots into the one index.
+    pub fn scan_roots(roots: Vec<PathBuf>, source: Arc<dyn DataSource>) -> Self {
+        Self {
+            tree: RwLock::new(Tree::scan(&roots, source.as_ref())),
+            roots,
+            source,
+        }
+    }
+    fn tree(&self) -> RwLockReadGuard<'_, Tree> {
+        self.tree.read().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_files::TestFiles;
+
+    #[test]
+    fn indexes_files_and_directories() {
+        let mocks = TestFiles::new();
+        mocks
+            .file("index.yml", "---")
+            .file("a/b.yml", "---")
+            .file(".e/f.yml", "---");
+        let root = mocks.path();
+        let index = DirIndex::scan(root);
+        assert!(index.is_dir(root));
+        assert!(!index.is_dir(&root.join(".e")));
+        assert!(index.is_dir(&root.join("a")));
+        assert!(index.is_file(&root.join("a/b.yml")));
+        assert!(!index.is_file(&root.join("a")));
+        assert!(!index.is_dir(&root.join("c")));
+        let mut listing = index.list(root).unwrap();
+        listing.sort();
+        assert_eq!(
+            listing,
+            vec![OsString::from("a"), OsString::from("index.yml")]
+        );
+        assert_eq!(index.list(&root.join("index.yml")), None);
+
+        mocks.file("c/d.yml", "---");
+        assert!(!index.is_file(&root.join("c/d.yml")));
+        index.refresh();
+        assert!(index.is_dir(&root.join("c")));
+        assert!(index.is_file(&root.join("c/d.yml")));
+    }
+}
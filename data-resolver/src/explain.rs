@@ -0,0 +1,77 @@
+//! Resolution trails.
+//!
+//! [DataResolver::explain()](crate::DataResolver::explain()) walks the data files
+//! consulted when resolving an address, in the order described in
+//! [data_path](crate::DataPath), recording a [ResolutionStep] for each.
+use serde::Serialize;
+use serde_yaml::Value;
+use std::fmt;
+use std::path::PathBuf;
+
+/// One data file consulted when resolving an address.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ResolutionStep {
+    /// Path of the data file
+    pub file: PathBuf,
+    /// Whether the file exists
+    pub exists: bool,
+    /// Data address looked up within the file
+    pub address: Vec<String>,
+    /// Position within the resolved value at which the file's data is merged
+    pub position: Vec<String>,
+    /// Value found at `address` in the file, if any
+    pub contributed: Option<Value>,
+    /// Error raised reading or merging the file, if any
+    pub error: Option<String>,
+    /// Value resolved so far, after merging this file's contribution
+    pub merged: Value,
+}
+
+impl fmt::Display for ResolutionStep {
+    /// Renders the step over several lines, e.g.
+    ///
+    /// ```text
+    /// a/b.yml
+    ///     address: c
+    ///     position: (root)
+    ///     contributed:
+    ///         name: Biggy
+    ///     merged:
+    ///         id: 14
+    ///         name: Biggy
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.file.display())?;
+        if !self.exists {
+            return write!(f, " (not found)");
+        }
+        write!(f, "\n    address: {}", dotted(&self.address))?;
+        write!(f, "\n    position: {}", dotted(&self.position))?;
+        match &self.contributed {
+            Some(value) => write!(f, "\n    contributed:\n{}", indented(value))?,
+            None => write!(f, "\n    contributed: nothing")?,
+        }
+        if let Some(error) = &self.error {
+            write!(f, "\n    error: {}", error)?;
+        }
+        write!(f, "\n    merged:\n{}", indented(&self.merged))
+    }
+}
+
+fn dotted(address: &[String]) -> String {
+    match address.is_empty() {
+        true => "(root)".to_string(),
+        false => address.join("."),
+    }
+}
+
+fn indented(value: &Value) -> String {
+    serde_yaml::to_string(value)
+        .unwrap_or_default()
+        .trim_start_matches("---")
+        .trim_start_matches([' ', '\n'])
+        .lines()
+        .map(|line| format!("        {}", line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,417 @@
+//! Data file formats.
+//!
+//! A [DataFormat] knows which file extensions it handles, and how to parse file
+//! content into a [serde_yaml::Value].  [DataPath](crate::DataPath) consults a
+//! [DataFormats] registry to decide which files to look for, and how to read them.
+//!
+//! Built-in formats are enabled by cargo features:
+//!
+//! - `yaml` (default): [Yaml], for `.yaml` and `.yml` files
+//! - `json` (default): [Json], for `.json` files
+//! - `toml`: [Toml], for `.toml` files
+//!
+//! Anything else can be supported by implementing [DataFormat] and registering it
+//! with [DataResolver::with_format](crate::DataResolver::with_format()), e.g.
+//!
+//! ```
+//! use confql_data_resolver::{DataFormat, DataResolver, DataResolverError};
+//! use serde_yaml::{Mapping, Value};
+//! use std::path::PathBuf;
+//!
+//! /// `.env` style `KEY=value` lines
+//! struct Env;
+//!
+//! impl DataFormat for Env {
+//!     fn extensions(&self) -> &[&str] {
+//!         &["env"]
+//!     }
+//!     fn parse(&self, content: &str) -> Result<Value, DataResolverError> {
+//!         let mut mapping = Mapping::new();
+//!         for line in content.lines().filter(|l| !l.trim().is_empty()) {
+//!             let (k, v) = line
+//!                 .split_once('=')
+//!                 .ok_or_else(|| DataResolverError::FormatError(line.into()))?;
+//!             mapping.insert(Value::from(k.trim()), Value::from(v.trim()));
+//!         }
+//!         Ok(Value::Mapping(mapping))
+//!     }
+//! }
+//!
+//! let resolver = DataResolver::from(PathBuf::from("data")).with_format(Env);
+//! ```
+use serde_yaml::Value;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::values::documents_from_file;
+#[cfg(feature = "yaml")]
+use super::values::{is_unset_tag, merge_documents, unset};
+use super::{DataResolverError, DataSource};
+
+/// A data file format, which can be registered with [DataFormats].
+pub trait DataFormat: Send + Sync {
+    /// File extensions (without leading `.`) handled by this format.  Where
+    /// files with several of these extensions share a stem, later extensions
+    /// take precedence.
+    fn extensions(&self) -> &[&str];
+    /// Parse file content into a [serde_yaml::Value].
+    fn parse(&self, content: &str) -> Result<Value, DataResolverError>;
+    /// Parse file content into one [serde_yaml::Value] per document.  Override
+    /// this for formats supporting several documents per file.
+    fn parse_documents(&self, content: &str) -> Result<Vec<Value>, DataResolverError> {
+        Ok(vec![self.parse(content)?])
+    }
+}
+
+/// YAML format, for `.yaml` and `.yml` files.
+#[cfg(feature = "yaml")]
+pub struct Yaml;
+
+#[cfg(feature = "yaml")]
+impl DataFormat for Yaml {
+    fn extensions(&self) -> &[&str] {
+        &["yaml", "yml"]
+    }
+    fn parse(&self, content: &str) -> Result<Value, DataResolverError> {
+        merge_documents(self.parse_documents(content)?)
+    }
+    /// Nodes tagged `!unset` are read as the [UNSET](crate::UNSET) tombstone, while
+    /// any other tag is ignored.
+    fn parse_documents(&self, content: &str) -> Result<Vec<Value>, DataResolverError> {
+        use serde::Deserialize;
+        let documents = serde_yaml::Deserializer::from_str(content)
+            .map(|document| YamlNode::deserialize(document).map(|node| node.0))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| match e.location() {
+                // errors shared between documents don't expose their location, but
+                // the same error reading the content as a whole does
+                None => serde_yaml::from_str::<Value>(content)
+                    .err()
+                    .filter(|e| e.location().is_some())
+                    .unwrap_or(e),
+                Some(_) => e,
+            })?;
+        Ok(documents)
+    }
+}
+
+/// A YAML node read as a [Value], where serde_yaml reports tags as enum variants.
+#[cfg(feature = "yaml")]
+struct YamlNode(Value);
+
+#[cfg(feature = "yaml")]
+impl<'de> serde::Deserialize<'de> for YamlNode {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(YamlNodeVisitor)
+    }
+}
+
+#[cfg(feature = "yaml")]
+struct YamlNodeVisitor;
+
+#[cfg(feature = "yaml")]
+impl<'de> serde::de::Visitor<'de> for YamlNodeVisitor {
+    type Value = YamlNode;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("any YAML value")
+    }
+    fn visit_bool<E>(self, v: bool) -> Result<YamlNode, E> {
+        Ok(YamlNode(Value::from(v)))
+    }
+    fn visit_i64<E>(self, v: i64) -> Result<YamlNode, E> {
+        Ok(YamlNode(Value::from(v)))
+    }
+    fn visit_u64<E>(self, v: u64) -> Result<YamlNode, E> {
+        Ok(YamlNode(Value::from(v)))
+    }
+    fn visit_f64<E>(self, v: f64) -> Result<YamlNode, E> {
+        Ok(YamlNode(Value::from(v)))
+    }
+    fn visit_str<E>(self, v: &str) -> Result<YamlNode, E> {
+        Ok(YamlNode(Value::from(v)))
+    }
+    fn visit_string<E>(self, v: String) -> Result<YamlNode, E> {
+        Ok(YamlNode(Value::from(v)))
+    }
+    fn visit_unit<E>(self) -> Result<YamlNode, E> {
+        Ok(YamlNode(Value::Null))
+    }
+    fn visit_none<E>(self) -> Result<YamlNode, E> {
+        Ok(YamlNode(Value::Null))
+    }
+    fn visit_some<D: serde::Deserializer<'de>>(self, d: D) -> Result<YamlNode, D::Error> {
+        serde::Deserialize::deserialize(d)
+    }
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<YamlNode, A::Error> {
+        let mut items = vec![];
+        while let Some(YamlNode(item)) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(YamlNode(Value::Sequence(items)))
+    }
+    fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> Result<YamlNode, A::Error> {
+        let mut mapping = serde_yaml::Mapping::new();
+        while let Some((YamlNode(k), YamlNode(v))) = map.next_entry()? {
+            mapping.insert(k, v);
+        }
+        Ok(YamlNode(Value::Mapping(mapping)))
+    }
+    fn visit_enum<A: serde::de::EnumAccess<'de>>(self, data: A) -> Result<YamlNode, A::Error> {
+        use serde::de::VariantAccess;
+        let (tag, node) = data.variant::<String>()?;
+        let YamlNode(value) = node.newtype_variant()?;
+        Ok(YamlNode(match is_unset_tag(&tag) {
+            true => unset(),
+            false => value,
+        }))
+    }
+}
+
+/// JSON format, for `.json` files.
+#[cfg(feature = "json")]
+pub struct Json;
+
+#[cfg(feature = "json")]
+impl DataFormat for Json {
+    fn extensions(&self) -> &[&str] {
+        &["json"]
+    }
+    fn parse(&self, content: &str) -> Result<Value, DataResolverError> {
+        Ok(serde_json::from_str(content)?)
+    }
+}
+
+/// TOML format, for `.toml` files.
+#[cfg(feature = "toml")]
+pub struct Toml;
+
+#[cfg(feature = "toml")]
+impl DataFormat for Toml {
+    fn extensions(&self) -> &[&str] {
+        &["toml"]
+    }
+    fn parse(&self, content: &str) -> Result<Value, DataResolverError> {
+        Ok(toml::from_str(content)?)
+    }
+}
+
+/// An ordered collection of [DataFormat]s.  Where files of different formats
+/// share a stem, formats registered later take precedence.
+#[derive(Clone)]
+pub struct DataFormats {
+    formats: Vec<Arc<dyn DataFormat>>,
+}
+
+impl DataFormats {
+    /// Creates an instance with no formats registered.
+    pub fn empty() -> Self {
+        Self { formats: vec![] }
+    }
+    /// Returns the recognised data file extensions, in merge order.
+    pub fn extensions(&self) -> impl Iterator<Item = &str> {
+        self.formats
+            .iter()
+            .flat_map(|f| f.extensions().iter().copied())
+    }
+    fn format_for(&self, path: &Path) -> Option<&dyn DataFormat> {
+        let ext = path.extension().and_then(OsStr::to_str)?;
+        self.formats
+            .iter()
+            .rev()
+            .find(|f| f.extensions().contains(&ext))
+            .map(|f| f.as_ref())
+    }
+    /// Registers a format, taking precedence over those already registered.
+    pub fn register<F: DataFormat + 'static>(&mut self, format: F) -> &mut Self {
+        self.formats.push(Arc::new(format));
+        self
+    }
+    fn registered_format(&self, path: &Path) -> Result<&dyn DataFormat, DataResolverError> {
+        self.format_for(path)
+            .ok_or_else(|| DataResolverError::UnknownFormat(path.to_path_buf()))
+    }
+    /// Returns `path` with each recognised extension in turn appended, in merge order.
+    /// Any dots already in the file name are kept, e.g. `a.b` gives `a.b.yml`.
+    pub fn paths_for(&self, path: &Path) -> Vec<PathBuf> {
+        self.extensions()
+            .map(|ext| {
+                let mut file = path.as_os_str().to_owned();
+                file.push(".");
+                file.push(ext);
+                PathBuf::from(file)
+            })
+            .collect()
+    }
+    /// Removes a recognised data file extension from a file name, if present.
+    pub fn strip_extension(&self, file_name: OsString) -> OsString {
+        let path = Path::new(&file_name);
+        match (path.file_stem(), self.format_for(path)) {
+            (Some(stem), Some(_)) => stem.to_owned(),
+            _ => file_name,
+        }
+    }
+    /// Reads the [Value] of each document in a file of `source`, using the format
+    /// registered for its extension.
+    pub fn documents_from_file(
+        &self,
+        path: &Path,
+        source: &dyn DataSource,
+    ) -> Result<Vec<Value>, DataResolverError> {
+        documents_from_file(path, self.registered_format(path)?, source)
+    }
+}
+
+impl Default for DataFormats {
+    /// All formats enabled by cargo features, in the order TOML, JSON, YAML.
+    fn default() -> Self {
+        #[allow(unused_mut)]
+        let mut formats = Self::empty();
+        #[cfg(feature = "toml")]
+        formats.register(Toml);
+        #[cfg(feature = "json")]
+        formats.register(Json);
+        #[cfg(feature = "yaml")]
+        formats.register(Yaml);
+        formats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use color_eyre::Result;
+
+    #[test]
+    fn default_extensions_in_merge_order() {
+        let formats = DataFormats::default();
+        assert_eq!(
+            formats.extensions().collect::<Vec<_>>(),
+            vec![
+                #[cfg(feature = "toml")]
+                "toml",
+                #[cfg(feature = "json")]
+                "json",
+                #[cfg(feature = "yaml")]
+                "yaml",
+                #[cfg(feature = "yaml")]
+                "yml"
+            ]
+        );
+    }
+
+    #[test]
+    fn strips_only_known_extensions() {
+        let formats = DataFormats::default();
+        #[cfg(feature = "yaml")]
+        assert_eq!(formats.strip_extension("a.yml".into()), "a");
+        #[cfg(feature = "json")]
+        assert_eq!(formats.strip_extension("a.json".into()), "a");
+        assert_eq!(formats.strip_extension("a.md".into()), "a.md");
+        assert_eq!(formats.strip_extension("a".into()), "a");
+        #[cfg(feature = "yaml")]
+        assert_eq!(formats.strip_extension("a.b.yml".into()), "a.b");
+    }
+
+    #[test]
+    fn paths_keep_dotted_file_names() {
+        let formats = DataFormats::default();
+        assert!(formats
+            .paths_for(Path::new("hosts/web01.example.com"))
+            .contains(&PathBuf::from("hosts/web01.example.com.yml")));
+    }
+
+    struct Upper;
+
+    impl DataFormat for Upper {
+        fn extensions(&self) -> &[&str] {
+            &["upper"]
+        }
+        fn parse(&self, content: &str) -> Result<Value, DataResolverError> {
+            Ok(Value::from(content.trim().to_uppercase()))
+        }
+    }
+
+    #[test]
+    fn registers_custom_format() -> Result<()> {
+        let mocks = test_files::TestFiles::new();
+        mocks.file("shout.upper", "hello");
+        let mut formats = DataFormats::default();
+        formats.register(Upper);
+        let documents =
+            formats.documents_from_file(&mocks.path().join("shout.upper"), &crate::FileSystem)?;
+        assert_eq!(documents, vec![Value::from("HELLO")]);
+        Ok(())
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn parses_yaml_documents() -> Result<()> {
+        let documents = Yaml.parse_documents("---\na: 1\n---\nb: 2\n")?;
+        assert_eq!(
+            documents,
+            vec![test_utils::yaml! {"a: 1"}, test_utils::yaml! {"b: 2"}]
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn parses_yaml_documents_merged() -> Result<()> {
+        let value = Yaml.parse("---\na: 1\n---\nb: 2\n")?;
+        assert_eq!(value, test_utils::yaml! {"{a: 1, b: 2}"});
+        Ok(())
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn parses_yaml_unset_tags() -> Result<()> {
+        let documents = Yaml.parse_documents(indoc::indoc! {"
+            ---
+            a: !unset
+            b:
+              - 1
+              - !unset ~
+              - c: !unset
+            ---
+            !unset
+            ---
+            d: &d !custom 2
+            e: *d
+        "})?;
+        assert_eq!(
+            documents,
+            vec![
+                test_utils::yaml! {"
+                    ---
+                    a: !unset
+                    b:
+                      - 1
+                      - !unset
+                      - c: !unset
+                "},
+                unset(),
+                test_utils::yaml! {"{d: 2, e: 2}"},
+            ]
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn locates_yaml_errors() {
+        for content in ["[x\n", "---\na: 1\n---\n[x\n"] {
+            let error = Yaml.parse_documents(content).unwrap_err();
+            assert!(error.location().is_some(), "no location for {:?}", error);
+        }
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn parses_toml() -> Result<()> {
+        let value = Toml.parse("[a]\nb = 1\n")?;
+        assert_eq!(value, test_utils::yaml! {"a: {b: 1}"});
+        Ok(())
+    }
+}
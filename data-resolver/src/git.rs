@@ -0,0 +1,291 @@
+//! Data at a git revision.
+//!
+//! Where the data root is tracked in git, a [GitSource] reads data as it was at a
+//! given commit, branch or tag, rather than as it is in the working tree.  Paths
+//! are the same either way, so a [DataResolver](crate::DataResolver) for the
+//! working tree may be switched to a revision with
+//! [at_revision()](crate::DataResolver::at_revision()).  Available with the `git`
+//! feature.
+//!
+//! Walking a revision's tree takes a while for a large repository, so a resolver
+//! keeps the [GitSource]s it has opened most recently, by commit id, along with
+//! their caches of parsed files.  Only a few are kept, since revisions may be
+//! named by clients, e.g. with an `asOf` query argument.
+use git2::{ObjectType, Oid, Repository, TreeWalkMode, TreeWalkResult};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::{DataResolverError, DataSource, FileCache, FileVersion};
+
+/// Files and directories of a git repository at a particular revision.
+pub struct GitSource {
+    repo: Mutex<Repository>,
+    dirs: HashMap<PathBuf, Vec<OsString>>,
+    files: HashMap<PathBuf, Oid>,
+}
+
+impl GitSource {
+    /// Opens the repository containing `path` at `revision`, e.g. a commit hash,
+    /// branch or tag, with paths beneath the repository's working directory.
+    pub fn open<P: AsRef<Path>>(path: P, revision: &str) -> Result<Self, DataResolverError> {
+        let repo = Repository::discover(path)?;
+        let commit = commit_id(&repo, revision)?;
+        Self::at_commit(repo, commit)
+    }
+    fn at_commit(repo: Repository, commit: Oid) -> Result<Self, DataResolverError> {
+        let base = repo.workdir().unwrap_or_else(|| repo.path());
+        let base = base.canonicalize().unwrap_or_else(|_| base.to_path_buf());
+        let mut dirs = HashMap::<PathBuf, Vec<OsString>>::new();
+        let mut files = HashMap::new();
+        dirs.insert(base.clone(), vec![]);
+        {
+            let tree = repo.find_commit(commit)?.tree()?;
+            tree.walk(TreeWalkMode::PreOrder, |parent, entry| {
+                let name = match entry.name() {
+                    Some(name) => name,
+                    None => return TreeWalkResult::Skip,
+                };
+                let dir = base.join(parent);
+                let path = dir.join(name);
+                match entry.kind() {
+                    Some(ObjectType::Tree) => {
+                        dirs.insert(path, vec![]);
+                    }
+                    Some(ObjectType::Blob) => {
+                        files.insert(path, entry.id());
+                    }
+                    // e.g. submodules
+                    _ => return TreeWalkResult::Skip,
+                }
+                if let Some(names) = dirs.get_mut(&dir) {
+                    names.push(name.into());
+                }
+                TreeWalkResult::Ok
+            })?;
+        }
+        Ok(Self {
+            repo: Mutex::new(repo),
+            dirs,
+            files,
+        })
+    }
+}
+
+impl DataSource for GitSource {
+    fn is_dir(&self, path: &Path) -> bool {
+        self.dirs.contains_key(path)
+    }
+    fn is_file(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+    fn list_dir(&self, path: &Path) -> Option<Vec<OsString>> {
+        self.dirs.get(path).cloned()
+    }
+    fn read_file(&self, path: &Path) -> io::Result<String> {
+        let id = self.files.get(path).ok_or(io::ErrorKind::NotFound)?;
+        let repo = self.repo.lock().unwrap_or_else(|e| e.into_inner());
+        let blob = repo.find_blob(*id).map_err(io::Error::other)?;
+        String::from_utf8(blob.content().to_vec())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+    /// Blobs never change, so only their size is given, read from the object's
+    /// header rather than its content.
+    fn version(&self, path: &Path) -> io::Result<FileVersion> {
+        let id = self.files.get(path).ok_or(io::ErrorKind::NotFound)?;
+        let repo = self.repo.lock().unwrap_or_else(|e| e.into_inner());
+        let (len, _) = repo
+            .odb()
+            .and_then(|odb| odb.read_header(*id))
+            .map_err(io::Error::other)?;
+        Ok(FileVersion {
+            modified: None,
+            len: len as u64,
+        })
+    }
+}
+
+/// The id of the commit `revision` names in `repo`.
+fn commit_id(repo: &Repository, revision: &str) -> Result<Oid, DataResolverError> {
+    Ok(repo.revparse_single(revision)?.peel_to_commit()?.id())
+}
+
+/// A [GitSource] with its cache of parsed files.
+type Revision = (Arc<GitSource>, Arc<FileCache>);
+
+/// A repository's path, with the id of a commit in it.
+type Key = (PathBuf, Oid);
+
+/// Most [Revision]s a resolver keeps, the least recently used being dropped first.
+const REVISIONS: usize = 8;
+
+/// [Revision]s opened by a resolver, by repository and commit id, the most
+/// recently used last.
+#[derive(Default)]
+pub(crate) struct Revisions(Mutex<Vec<(Key, Revision)>>);
+
+impl Revisions {
+    /// The source for `revision` of the repository containing every one of `roots`,
+    /// opening it unless it was recently opened at the same commit.  Roots in
+    /// different repositories have no revision in common, so are an error, while
+    /// without roots there is nothing to open.
+    pub(crate) fn open(
+        &self,
+        roots: &[PathBuf],
+        revision: &str,
+    ) -> Result<Option<Revision>, DataResolverError> {
+        let repos = roots
+            .iter()
+            .map(Repository::discover)
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut paths: Vec<_> = repos.iter().map(|repo| repo.path().to_path_buf()).collect();
+        paths.sort();
+        paths.dedup();
+        if paths.len() > 1 {
+            return Err(DataResolverError::GitRepositoriesDiffer(paths));
+        }
+        let repo = match repos.into_iter().next() {
+            Some(repo) => repo,
+            None => return Ok(None),
+        };
+        let key = (repo.path().to_path_buf(), commit_id(&repo, revision)?);
+        if let Some(opened) = self.get(&key) {
+            return Ok(Some(opened));
+        }
+        let opened = (
+            Arc::new(GitSource::at_commit(repo, key.1)?),
+            Arc::new(FileCache::new()),
+        );
+        let mut revisions = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(opened) = touch(&mut revisions, &key) {
+            return Ok(Some(opened));
+        }
+        revisions.push((key, opened.clone()));
+        if revisions.len() > REVISIONS {
+            revisions.remove(0);
+        }
+        Ok(Some(opened))
+    }
+    fn get(&self, key: &Key) -> Option<Revision> {
+        let mut revisions = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        touch(&mut revisions, key)
+    }
+}
+
+/// The [Revision] at `key`, moved to be the most recently used.
+fn touch(revisions: &mut Vec<(Key, Revision)>, key: &Key) -> Option<Revision> {
+    let i = revisions.iter().position(|(k, _)| k == key)?;
+    let entry = revisions.remove(i);
+    let opened = entry.1.clone();
+    revisions.push(entry);
+    Some(opened)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DataResolver;
+    use color_eyre::Result;
+    use git2::{Signature, Time};
+    use test_files::TestFiles;
+
+    fn commit(repo: &Repository, message: &str) -> Result<Oid> {
+        let mut index = repo.index()?;
+        index.add_all(["*"], git2::IndexAddOption::DEFAULT, None)?;
+        index.write()?;
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let signature = Signature::new("test", "test@example.com", &Time::new(0, 0))?;
+        let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let parents: Vec<_> = parent.iter().collect();
+        Ok(repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )?)
+    }
+
+    #[test]
+    fn resolves_at_revision() -> Result<()> {
+        let mocks = TestFiles::new();
+        let repo = Repository::init(mocks.path())?;
+        mocks.file("data/a.yml", "b: 1").file("data/a/c.yml", "2");
+        let first = commit(&repo, "first")?;
+        repo.tag_lightweight("v1", &repo.find_object(first, None)?, false)?;
+        mocks.file("data/a.yml", "b: 3").file("data/d.yml", "4");
+        commit(&repo, "second")?;
+        mocks.file("data/a.yml", "b: 5");
+
+        let resolver = DataResolver::from(mocks.path().join("data"));
+        assert_eq!(resolver.get::<i32>(&["a", "b"])?, 5);
+        for (revision, b, d) in [("v1", 1, None), ("HEAD", 3, Some(4))] {
+            let resolver = resolver.at_revision(revision)?;
+            assert_eq!(resolver.get::<i32>(&["a", "b"])?, b);
+            assert_eq!(resolver.get::<i32>(&["a", "c"])?, 2);
+            assert_eq!(resolver.get::<Option<i32>>(&["d"])?, d);
+        }
+        assert!(resolver.at_revision("v2").is_err());
+        // revisions naming the same commit share a source
+        let tag = resolver.at_revision("v1")?;
+        let commit = resolver.at_revision(&first.to_string())?;
+        assert!(Arc::ptr_eq(&tag.source, &commit.source));
+        assert!(!Arc::ptr_eq(
+            &tag.source,
+            &resolver.at_revision("HEAD")?.source
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_only_recent_revisions() -> Result<()> {
+        let mocks = TestFiles::new();
+        let repo = Repository::init(mocks.path())?;
+        let mut commits = vec![];
+        for i in 0..=REVISIONS {
+            mocks.file("a.yml", &i.to_string());
+            commits.push(commit(&repo, "next")?.to_string());
+        }
+        let resolver = DataResolver::from(mocks.path().to_path_buf());
+        let first = resolver.at_revision(&commits[0])?;
+        for (i, commit) in commits.iter().enumerate() {
+            assert_eq!(resolver.at_revision(commit)?.get::<i32>(&["a"])?, i as i32);
+        }
+        assert!(!Arc::ptr_eq(
+            &first.source,
+            &resolver.at_revision(&commits[0])?.source
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_layered_roots_at_revision() -> Result<()> {
+        let mocks = TestFiles::new();
+        let repo = Repository::init(mocks.path().join("repo"))?;
+        mocks
+            .file("repo/base/a.yml", "{b: 1, c: 2}")
+            .file("repo/prod/a.yml", "c: 3");
+        commit(&repo, "first")?;
+        mocks.file("repo/prod/a.yml", "c: 4");
+
+        let roots = vec![
+            mocks.path().join("repo/base"),
+            mocks.path().join("repo/prod"),
+        ];
+        let resolver = DataResolver::from(roots.clone()).at_revision("HEAD")?;
+        assert_eq!(resolver.get::<i32>(&["a", "b"])?, 1);
+        assert_eq!(resolver.get::<i32>(&["a", "c"])?, 3);
+
+        Repository::init(mocks.path().join("other"))?;
+        mocks.file("other/a.yml", "c: 5");
+        let roots = vec![roots[0].clone(), mocks.path().join("other")];
+        assert!(matches!(
+            DataResolver::from(roots).at_revision("HEAD"),
+            Err(DataResolverError::GitRepositoriesDiffer(_))
+        ));
+        Ok(())
+    }
+}
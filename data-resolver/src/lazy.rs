@@ -0,0 +1,132 @@
+//! Lazy resolution.
+//!
+//! Resolving an object type with [DataResolver::get()](crate::DataResolver::get())
+//! resolves every field beneath it, whether or not a query selects them.  Instead, the
+//! struct generated for an object type declared with `@confql(lazy: true)` just holds a
+//! [LazyValue], from which each field is resolved only when queried.
+//!
+//! A [LazyValue] is usually a data address, but where the object was resolved as part
+//! of something bigger, e.g. as a list item, it holds the value already resolved, so
+//! fields are taken from that.
+use serde::{Deserialize, Deserializer};
+use serde_yaml::Value;
+
+use super::{DataResolver, DataResolverError, ResolveValue};
+
+/// Data of an object, resolved field by field.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LazyValue {
+    /// Data address of the object, yet to be resolved
+    At(Vec<String>),
+    /// Value of the object, already resolved
+    Resolved(Value),
+}
+
+impl LazyValue {
+    /// Data of the object at a data address.
+    pub fn at(address: &[&str]) -> Self {
+        Self::At(address.iter().map(|k| k.to_string()).collect())
+    }
+    /// Data of a field of the object.
+    pub fn child(&self, field: &str) -> Self {
+        match self {
+            Self::At(address) => {
+                let mut address = address.clone();
+                address.push(field.to_string());
+                Self::At(address)
+            }
+            Self::Resolved(value) => Self::Resolved(value.get(field).cloned().unwrap_or_default()),
+        }
+    }
+    /// Whether there is any data for the object.  See [DataResolver::exists()].
+    pub fn exists(&self, resolver: &DataResolver) -> bool {
+        match self {
+            Self::At(address) => resolver.exists(&keys(address)),
+            Self::Resolved(value) => !value.is_null(),
+        }
+    }
+    /// As [exists](LazyValue::exists()), but without blocking the calling task.  See
+    /// [DataResolver::get_async()].
+    #[cfg(feature = "async")]
+    pub async fn exists_async(&self, resolver: &DataResolver) -> bool {
+        match self {
+            Self::At(address) => resolver.exists_async(&keys(address)).await,
+            Self::Resolved(value) => !value.is_null(),
+        }
+    }
+    /// Resolves a field of the object, as an instance of `R`.
+    pub fn resolve<R: ResolveValue>(
+        &self,
+        resolver: &DataResolver,
+        field: &str,
+    ) -> Result<Value, DataResolverError> {
+        self.child(field).value::<R>(resolver)
+    }
+    /// As [resolve](LazyValue::resolve()), but without blocking the calling task.
+    #[cfg(feature = "async")]
+    pub async fn resolve_async<R: ResolveValue + Send + 'static>(
+        &self,
+        resolver: &DataResolver,
+        field: &str,
+    ) -> Result<Value, DataResolverError> {
+        self.child(field).value_async::<R>(resolver).await
+    }
+    /// Resolves the whole object, as an instance of `R`.
+    pub fn value<R: ResolveValue>(
+        &self,
+        resolver: &DataResolver,
+    ) -> Result<Value, DataResolverError> {
+        match self {
+            Self::At(address) => resolver.resolve::<R>(&keys(address)),
+            Self::Resolved(value) => Ok(value.clone()),
+        }
+    }
+    /// As [value](LazyValue::value()), but without blocking the calling task.
+    #[cfg(feature = "async")]
+    pub async fn value_async<R: ResolveValue + Send + 'static>(
+        &self,
+        resolver: &DataResolver,
+    ) -> Result<Value, DataResolverError> {
+        match self {
+            Self::At(address) => resolver.resolve_async::<R>(&keys(address)).await,
+            Self::Resolved(value) => Ok(value.clone()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for LazyValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Value::deserialize(deserializer).map(Self::Resolved)
+    }
+}
+
+fn keys(address: &[String]) -> Vec<&str> {
+    address.iter().map(String::as_str).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use color_eyre::Result;
+    use test_files::TestFiles;
+    use test_utils::yaml;
+
+    #[test]
+    fn resolves_fields_at_address_or_from_value() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks.file("a/b.yml", "{c: 1, d: {e: 2}}");
+        let resolver = DataResolver::from(mocks.path().to_path_buf());
+        let at = LazyValue::at(&["a", "b"]);
+        let resolved: LazyValue = serde_yaml::from_value(yaml! {"{c: 1, d: {e: 2}}"})?;
+        for lazy in [at, resolved] {
+            assert!(lazy.exists(&resolver));
+            assert!(!lazy.child("f").exists(&resolver));
+            assert_eq!(lazy.resolve::<i32>(&resolver, "c")?, Value::from(1));
+            assert_eq!(
+                lazy.child("d").resolve::<i32>(&resolver, "e")?,
+                Value::from(2)
+            );
+        }
+        Ok(())
+    }
+}
@@ -1,49 +1,226 @@
-//! Filesystem yaml data resolvers.
+//! Filesystem yaml (and other format) data resolvers.
 
 #![deny(missing_docs)]
 use juniper::ID;
 use serde::Deserialize;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use thiserror::Error;
 
+pub mod cache;
+pub use cache::{CacheMetrics, FileCache};
 mod data_path;
-pub use data_path::DataPath;
+pub use data_path::{DataPath, ErrorSink, DEFAULTS, EXTENDS};
+pub mod data_source;
+pub use data_source::{DataSource, FileSystem, FileVersion, MemorySource};
+pub mod dir_index;
+pub use dir_index::DirIndex;
+pub mod explain;
+pub use explain::ResolutionStep;
+pub mod formats;
+pub use formats::{DataFormat, DataFormats};
+#[cfg(feature = "git")]
+pub mod git;
+#[cfg(feature = "git")]
+pub use git::GitSource;
+pub mod lazy;
+pub use lazy::LazyValue;
+pub mod sources;
+use sources::render_sources;
+pub use sources::FieldSources;
 mod values;
-pub use values::Merge;
+pub use values::{is_unset, order_by, unset, Append, ByKey, Merge, Replace, Unique, UNSET};
+use values::{merge_documents, sequence_documents, strip_unset};
+#[cfg(feature = "watch")]
+pub mod watch;
+#[cfg(feature = "watch")]
+pub use watch::{DataChange, DataWatcher, ValueStream};
 
 /// Data resolution and value manipulation errors
 #[derive(Error, Debug)]
 pub enum DataResolverError {
+    /// Error raised while resolving data from a particular file, or directory
+    #[error("{}", describe_at_path(source, path, address))]
+    AtPath {
+        /// File (or directory) from which data was being resolved
+        path: PathBuf,
+        /// Data address being resolved within `path`
+        address: Vec<String>,
+        /// The error raised
+        source: Box<DataResolverError>,
+    },
     /// Merge attempted into a non-mapping (i.e. primitive or list)
     #[error("Cannot merge into non-mapping `{0:?}`")]
     CannotMergeIntoNonMapping(serde_yaml::Value),
+    /// Data [extends](EXTENDS) data which in turn extends it, as in the given cycle of
+    /// data addresses
+    #[error("Cycle of `{}`: {}", EXTENDS, .0.join(" -> "))]
+    ExtendsCycle(Vec<String>),
+    /// Data [extends](EXTENDS) a data address at which there is no data
+    #[error("No data at `{}: {0}`", EXTENDS)]
+    ExtendsNotFound(String),
+    /// Error raised by a custom [DataFormat]
+    #[error(transparent)]
+    FormatError(Box<dyn std::error::Error + Send + Sync>),
+    /// [git2::Error], e.g. opening a repository or finding a revision
+    #[cfg(feature = "git")]
+    #[error(transparent)]
+    GitError(#[from] git2::Error),
+    /// Data roots lie in different git repositories, so have no revision in common
+    #[cfg(feature = "git")]
+    #[error("Data roots are in different git repositories: {0:?}")]
+    GitRepositoriesDiffer(Vec<PathBuf>),
+    /// [ignore::Error], e.g. an invalid pattern in a `.confqlignore` file
+    #[error(transparent)]
+    IgnoreError(#[from] ignore::Error),
     /// Merge attempted of two types with no obvious general method of doing so
     #[error("Incompatible merge `{dst:?}` <- `{src:?}`")]
     IncompatibleYamlMerge {
         /// Source value which we were attempting to merge into destination
-        src: serde_yaml::Value,
+        src: Box<serde_yaml::Value>,
         /// Destination value into which we were attempting to merge source
-        dst: serde_yaml::Value,
+        dst: Box<serde_yaml::Value>,
     },
     /// [std::io::Error]
     #[error(transparent)]
     IOError(#[from] std::io::Error),
+    /// [serde_json::Error]
+    #[cfg(feature = "json")]
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
     /// Attempt made to access data at a non-existing key within a mapping
     #[error("Key `{0}` not found")]
     KeyNotFound(String),
+    /// Errors collected while resolving data in [strict](DataResolver::strict()) mode,
+    /// or in any mode, [cycles](DataResolverError::ExtendsCycle) of [EXTENDS]
+    #[error("Errors resolving data:{}", .0.iter().map(|e| format!("\n- {}", e)).collect::<String>())]
+    ResolutionErrors(Vec<DataResolverError>),
+    /// [toml::de::Error]
+    #[cfg(feature = "toml")]
+    #[error(transparent)]
+    TomlError(#[from] toml::de::Error),
+    /// Attempt made to read a file with no registered [DataFormat]
+    #[error("No data format registered for `{0:?}`")]
+    UnknownFormat(PathBuf),
+    /// [notify::Error]
+    #[cfg(feature = "watch")]
+    #[error(transparent)]
+    WatchError(#[from] notify::Error),
     /// [serde_yaml::Error]
     #[error(transparent)]
     YamlError(#[from] serde_yaml::Error),
 }
 
+impl DataResolverError {
+    /// Attributes this error to a file (or directory) and the data address being
+    /// resolved within it, unless it is already attributed.
+    pub fn at_path<P: Into<PathBuf>>(self, path: P, address: &[&str]) -> Self {
+        match self {
+            Self::AtPath { .. } => self,
+            _ => Self::AtPath {
+                path: path.into(),
+                address: address.iter().map(|k| k.to_string()).collect(),
+                source: Box::new(self),
+            },
+        }
+    }
+    /// The cycle of data addresses of an [ExtendsCycle](DataResolverError::ExtendsCycle)
+    /// error, if it is one.
+    fn extends_cycle(&self) -> Option<&[String]> {
+        match self {
+            Self::AtPath { source, .. } => source.extends_cycle(),
+            Self::ExtendsCycle(cycle) => Some(cycle),
+            _ => None,
+        }
+    }
+    /// Whether this and `other` are [ExtendsCycle](DataResolverError::ExtendsCycle)
+    /// errors naming the same cycle, from whichever address in it.
+    fn is_same_cycle(&self, other: &Self) -> bool {
+        // a cycle names its first address again at the end, and as each address extends
+        // just one other, no two cycles share the same addresses
+        let members = |e: &Self| {
+            e.extends_cycle().map(|cycle| {
+                let mut members = cycle[1..].to_vec();
+                members.sort();
+                members
+            })
+        };
+        members(self).is_some() && members(self) == members(other)
+    }
+    /// Whether this error just means there is no data at some position, i.e. a file
+    /// or key was not found, which is expected when resolving data.
+    pub fn is_missing_data(&self) -> bool {
+        match self {
+            Self::AtPath { source, .. } => source.is_missing_data(),
+            Self::IOError(e) => e.kind() == std::io::ErrorKind::NotFound,
+            Self::KeyNotFound(_) => true,
+            _ => false,
+        }
+    }
+    /// Line and column, counting from 1, at which a file failed to parse, if known.
+    pub fn location(&self) -> Option<(usize, usize)> {
+        match self {
+            Self::AtPath { source, .. } => source.location(),
+            #[cfg(feature = "json")]
+            Self::JsonError(e) if e.line() > 0 => Some((e.line(), e.column())),
+            #[cfg(feature = "toml")]
+            Self::TomlError(e) => e.line_col().map(|(line, col)| (line + 1, col + 1)),
+            Self::YamlError(e) => e.location().map(|l| (l.line(), l.column())),
+            _ => None,
+        }
+    }
+}
+
+/// Renders an [AtPath](DataResolverError::AtPath) error over several lines, e.g.
+///
+/// ```text
+/// did not find expected ',' or ']' at line 3 column 1, while parsing a flow sequence at line 2 column 1
+///     file: data/tags.yml, line 3, column 1
+///     address: (root)
+/// ```
+fn describe_at_path(source: &DataResolverError, path: &Path, address: &[String]) -> String {
+    let location = source
+        .location()
+        .map(|(line, column)| format!(", line {}, column {}", line, column))
+        .unwrap_or_default();
+    let address = match address.is_empty() {
+        true => "(root)".to_string(),
+        false => address.join("."),
+    };
+    format!(
+        "{}\n    file: {}{}\n    address: {}",
+        source,
+        path.display(),
+        location,
+        address
+    )
+}
+
 /// Clients interact with this struct for data resolution operations.
 /// In particular, this forms an important part of the `juniper::Context`
 /// generated by the procedural macros.  Essentially this holds a [PathBuf]
 /// pointing at the data root directory, and exposes a [get](DataResolver::get()) method for
 /// trying to resolve a generic type at a specified data address under
 /// that root directory.
+///
+/// A resolver may instead be created from several data roots, e.g. a `base` tree and
+/// an environment's overrides, in which case data at an address is resolved under
+/// each in turn, and merged over that from the roots before.
 pub struct DataResolver {
-    root: PathBuf,
+    roots: Vec<PathBuf>,
+    cache: Arc<FileCache>,
+    defaults: Arc<str>,
+    formats: Arc<DataFormats>,
+    index: Option<Arc<DirIndex>>,
+    profile: Option<Arc<str>>,
+    profiles: Arc<[String]>,
+    #[cfg(feature = "git")]
+    revisions: Arc<git::Revisions>,
+    source: Arc<dyn DataSource>,
+    sources: bool,
+    strict: bool,
+    #[cfg(feature = "watch")]
+    watcher: Arc<std::sync::Mutex<Option<DataWatcher>>>,
 }
 
 impl DataResolver {
@@ -54,15 +231,386 @@ impl DataResolver {
         T: for<'de> Deserialize<'de>,
         T: ResolveValue,
     {
-        let data_path = DataPath::new(&self.root, address);
-        let value = T::resolve_value(data_path)?;
+        self.get_with::<T, T>(address)
+    }
+    /// As [get](DataResolver::get()), but resolving data with the [ResolveValue] impl of
+    /// `R` in place of `T`'s, e.g. to apply a merge strategy such as [Replace].
+    pub fn get_with<T, R>(&self, address: &[&str]) -> Result<T, DataResolverError>
+    where
+        T: for<'de> Deserialize<'de>,
+        R: ResolveValue,
+    {
+        let value = self.resolve::<R>(address)?;
+        Ok(serde_yaml::from_value(value)?)
+    }
+    /// Resolve the [serde_yaml::Value] representing an instance of `R` at a specified
+    /// address under the data root directory, with lists in their final order.  Where
+    /// there are several data roots, the value under each is merged over the last, as
+    /// by [merge_value](ResolveValue::merge_value()).
+    pub fn resolve<R: ResolveValue>(
+        &self,
+        address: &[&str],
+    ) -> Result<serde_yaml::Value, DataResolverError> {
+        let errors = ErrorSink::default();
+        let cycles = ErrorSink::default();
+        let mut resolved: Option<serde_yaml::Value> = None;
+        let mut failures = vec![];
+        for root in self.roots.iter() {
+            let mut data_path = self
+                .data_path(root, address)
+                .collecting_cycles(cycles.clone());
+            if self.strict {
+                data_path = data_path.collecting_errors(errors.clone());
+            }
+            if self.sources {
+                data_path = data_path.tracking_sources_relative_to(self.base_dir());
+            }
+            match (R::resolve_value(data_path), &mut resolved) {
+                (Ok(value), Some(merged)) => {
+                    if let Err(e) = R::merge_value(merged, value) {
+                        failures.push(e);
+                    }
+                }
+                (Ok(value), None) => resolved = Some(value),
+                (Err(e), _) => failures.push(e),
+            }
+        }
+        // items which may merge with those of other roots are only merged over their
+        // defaults once data from every root is merged
+        if let (Some(value), [root, _, ..]) = (&mut resolved, self.roots.as_slice()) {
+            let mut data_path = self
+                .data_path(root, &[])
+                .join(address.iter().collect::<PathBuf>());
+            if self.strict {
+                data_path = data_path.collecting_errors(errors.clone());
+            }
+            R::apply_defaults(value, &data_path);
+        }
+        // data missing under some roots is only an error if missing under all
+        if resolved.is_some() {
+            failures.retain(|e| !e.is_missing_data());
+        }
+        let mut errors = std::mem::take(&mut *errors.lock().unwrap());
+        // data caught in a cycle of `_extends` is missing, so that's an error in any mode
+        for cycle in std::mem::take(&mut *cycles.lock().unwrap()) {
+            if !failures.iter().any(|e| e.is_same_cycle(&cycle)) {
+                errors.push(cycle);
+            }
+        }
+        if !errors.is_empty() {
+            errors.extend(failures);
+            return Err(DataResolverError::ResolutionErrors(errors));
+        }
+        if !failures.is_empty() {
+            return Err(failures.remove(0));
+        }
+        let mut value = strip_unset(resolved.unwrap_or_default());
+        if self.sources {
+            render_sources(&mut value);
+        }
+        R::order_value(&mut value);
+        Ok(value)
+    }
+    /// As [get](DataResolver::get()), but without blocking the calling task.  The
+    /// filesystem has no truly non-blocking interface, so, as with async file APIs
+    /// generally, data is read and resolved on a pool of threads set aside for
+    /// blocking work, rather than the async executor's.  Available with the `async`
+    /// feature.
+    #[cfg(feature = "async")]
+    pub async fn get_async<T>(&self, address: &[&str]) -> Result<T, DataResolverError>
+    where
+        T: for<'de> Deserialize<'de>,
+        T: ResolveValue + Send + 'static,
+    {
+        self.get_with_async::<T, T>(address).await
+    }
+    /// As [get_with](DataResolver::get_with()), but without blocking the calling task.
+    /// See [get_async](DataResolver::get_async()).
+    #[cfg(feature = "async")]
+    pub async fn get_with_async<T, R>(&self, address: &[&str]) -> Result<T, DataResolverError>
+    where
+        T: for<'de> Deserialize<'de>,
+        R: ResolveValue + Send + 'static,
+    {
+        let value = self.resolve_async::<R>(address).await?;
         Ok(serde_yaml::from_value(value)?)
     }
+    /// As [resolve](DataResolver::resolve()), but without blocking the calling task.
+    /// See [get_async](DataResolver::get_async()).
+    #[cfg(feature = "async")]
+    pub async fn resolve_async<R: ResolveValue + Send + 'static>(
+        &self,
+        address: &[&str],
+    ) -> Result<serde_yaml::Value, DataResolverError> {
+        let resolver = self.detached();
+        let address: Vec<String> = address.iter().map(|k| k.to_string()).collect();
+        blocking::unblock(move || {
+            let keys: Vec<&str> = address.iter().map(String::as_str).collect();
+            resolver.resolve::<R>(&keys)
+        })
+        .await
+    }
+    /// A resolver reading data as it was at `revision`, e.g. a commit hash, branch or
+    /// tag, of the git repository containing the data roots, rather than from the
+    /// working tree.  It is otherwise configured as this one.  A recently read commit
+    /// is not read again, however many resolvers are created at it.  See [git].
+    #[cfg(feature = "git")]
+    pub fn at_revision(&self, revision: &str) -> Result<Self, DataResolverError> {
+        let roots = self
+            .roots
+            .iter()
+            .map(|root| root.canonicalize())
+            .collect::<Result<Vec<_>, _>>()?;
+        let (source, cache) = match self.revisions.open(&roots, revision)? {
+            Some(opened) => opened,
+            None => return Ok(self.detached()),
+        };
+        let mut resolver = self.detached();
+        resolver.roots = roots;
+        resolver.index = None;
+        resolver.source = source;
+        resolver.cache = cache;
+        #[cfg(feature = "watch")]
+        {
+            resolver.watcher = Default::default();
+        }
+        Ok(resolver)
+    }
+    /// Returns lookup counts of the [cache] of parsed data files, which is shared by
+    /// every resolution, so that each file is only parsed again once it changes.
+    pub fn cache_metrics(&self) -> CacheMetrics {
+        self.cache.metrics()
+    }
+    /// Directory which data file names are given relative to, i.e. the data root, or
+    /// the deepest directory containing every data root.
+    fn base_dir(&self) -> PathBuf {
+        let mut roots = self.roots.iter();
+        let mut base = roots.next().cloned().unwrap_or_default();
+        for root in roots {
+            while !root.starts_with(&base) {
+                if !base.pop() {
+                    break;
+                }
+            }
+        }
+        base
+    }
+    fn data_path<'a>(&self, root: &Path, address: &'a [&'a str]) -> DataPath<'a> {
+        let data_path = DataPath::with_formats(root, address, self.formats.clone())
+            .caching(self.cache.clone())
+            .reading_from(self.source.clone())
+            .with_defaults_file(self.defaults.clone())
+            .with_profiles(self.profiles.clone())
+            .layered(self.roots.as_slice())
+            .deferring_defaults(self.roots.len() > 1);
+        let data_path = match &self.profile {
+            Some(profile) => data_path.in_profile(profile.clone()),
+            None => data_path,
+        };
+        match &self.index {
+            Some(index) => data_path.indexed(index.clone()),
+            None => data_path,
+        }
+    }
+    /// A resolver of its own for work which outlives a borrow of this one, sharing its
+    /// cache, index and any watcher started by [subscribe](DataResolver::subscribe()).
+    fn detached(&self) -> Self {
+        Self {
+            roots: self.roots.clone(),
+            cache: self.cache.clone(),
+            defaults: self.defaults.clone(),
+            formats: self.formats.clone(),
+            index: self.index.clone(),
+            profile: self.profile.clone(),
+            profiles: self.profiles.clone(),
+            #[cfg(feature = "git")]
+            revisions: self.revisions.clone(),
+            source: self.source.clone(),
+            sources: self.sources,
+            strict: self.strict,
+            #[cfg(feature = "watch")]
+            watcher: self.watcher.clone(),
+        }
+    }
+    /// Lists every data file consulted in resolving an address, in order, with the
+    /// data each contributed and the merged result after each.  See [explain].
+    pub fn explain(&self, address: &[&str]) -> Vec<ResolutionStep> {
+        let mut steps = vec![];
+        let mut merged = serde_yaml::Value::Null;
+        for root in self.roots.iter() {
+            self.data_path(root, address)
+                .explain_onto(&mut merged, &mut steps);
+        }
+        let base = self.base_dir();
+        for step in steps.iter_mut() {
+            if let Ok(file) = step.file.strip_prefix(&base) {
+                step.file = file.to_path_buf();
+            }
+        }
+        steps
+    }
+    /// Whether there is any data at a specified address under the data root directory,
+    /// without resolving it.
+    pub fn exists(&self, address: &[&str]) -> bool {
+        self.roots
+            .iter()
+            .any(|root| self.data_path(root, address).exists())
+    }
+    /// As [exists](DataResolver::exists()), but without blocking the calling task.
+    /// See [get_async](DataResolver::get_async()).
+    #[cfg(feature = "async")]
+    pub async fn exists_async(&self, address: &[&str]) -> bool {
+        let resolver = self.detached();
+        let address: Vec<String> = address.iter().map(|k| k.to_string()).collect();
+        blocking::unblock(move || {
+            let keys: Vec<&str> = address.iter().map(String::as_str).collect();
+            resolver.exists(&keys)
+        })
+        .await
+    }
+    /// A resolver for `profile`, e.g. `prod`, sharing this one's cache and index.  It
+    /// merges a profile's overlay of each data file, e.g. `a.prod.yml` for `a.yml`, or
+    /// `index.prod.yml` for `index.yml`, straight after the file itself.
+    pub fn in_profile(&self, profile: &str) -> Self {
+        let mut resolver = self.detached();
+        resolver.profile = Some(profile.into());
+        resolver
+    }
+    /// Sets whether to walk the data root up front into a [DirIndex], and consult that
+    /// rather than the filesystem for which files and directories exist.  Files added
+    /// or removed after that are not seen until the index is
+    /// [refreshed](DataResolver::refresh_index()).  See [dir_index].
+    pub fn indexed(mut self, indexed: bool) -> Self {
+        self.index = match indexed {
+            true => Some(Arc::new(DirIndex::scan_roots(
+                self.roots.clone(),
+                self.source.clone(),
+            ))),
+            false => None,
+        };
+        self
+    }
+    /// Walks the data root again to update the [DirIndex] of an
+    /// [indexed](DataResolver::indexed()) resolver.  Otherwise does nothing.
+    pub fn refresh_index(&self) {
+        if let Some(index) = &self.index {
+            index.refresh();
+        }
+    }
+    /// Sets whether to resolve data strictly.  A strict resolver fails with
+    /// [ResolutionErrors](DataResolverError::ResolutionErrors) where data could not be
+    /// read or merged, e.g. due to a malformed file, rather than skipping it.  Missing
+    /// files and keys are never an error.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+    /// Streams the [serde_yaml::Value] representing an instance of `R` at a specified
+    /// address, as [resolve](DataResolver::resolve()) does, then again each time data
+    /// files at, above or beneath that address change, skipping unchanged values.  The
+    /// data root is [watched](DataResolver::watch()) from the first subscription for
+    /// as long as the resolver, or any [clone](Clone) of it, lives.
+    #[cfg(feature = "watch")]
+    pub fn subscribe<R: ResolveValue + 'static>(
+        &self,
+        address: &[&str],
+    ) -> Result<ValueStream, DataResolverError> {
+        let mut watcher = self.watcher.lock().unwrap_or_else(|e| e.into_inner());
+        let changes = match &mut *watcher {
+            Some(watcher) => watcher.subscribe_stream(),
+            unwatched => {
+                let watcher = self.watch()?;
+                let changes = watcher.subscribe_stream();
+                *unwatched = Some(watcher);
+                changes
+            }
+        };
+        Ok(watch::subscribe::<R>(self.detached(), address, changes))
+    }
+    /// Sets whether to track which data files each value was resolved from, for
+    /// the `_sources` field of object types declared with `@confql(sources: true)`.
+    /// See [sources].
+    pub fn track_sources(mut self, sources: bool) -> Self {
+        self.sources = sources;
+        self
+    }
+    /// Watches the data root, keeping cached data and any [DirIndex] up to date as files
+    /// change, until the returned [DataWatcher] is dropped.  See [watch].
+    #[cfg(feature = "watch")]
+    pub fn watch(&self) -> Result<DataWatcher, DataResolverError> {
+        watch::watch(&self.roots, self.cache.clone(), self.index.clone())
+    }
+    /// Reads defaults for list items from files with stem `stem`, e.g. `_base` for
+    /// `_base.yml`, rather than [DEFAULTS].  See [DataPath::defaults()].
+    pub fn with_defaults_file<S: Into<Arc<str>>>(mut self, stem: S) -> Self {
+        self.defaults = stem.into();
+        self
+    }
+    /// Registers an additional [DataFormat], taking precedence over those already
+    /// recognised where files share a stem.  See [formats] for an example.
+    pub fn with_format<F: DataFormat + 'static>(mut self, format: F) -> Self {
+        Arc::make_mut(&mut self.formats).register(format);
+        self.cache.clear();
+        self
+    }
+    /// Names the profiles in use, e.g. `prod` and `staging`, so that their overlay files
+    /// are never list items of their own, even when resolving in no profile or another.
+    /// Without, only overlays in the [active profile](DataResolver::in_profile()) are
+    /// told apart from items with dotted names.
+    pub fn with_profiles<I, S>(mut self, profiles: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.profiles = profiles.into_iter().map(Into::into).collect();
+        self
+    }
+    /// Reads data from `source`, e.g. a [MemorySource], rather than the filesystem.
+    /// The data root is then a path in `source`.  See [data_source].
+    pub fn with_source<S: DataSource + 'static>(mut self, source: S) -> Self {
+        self.source = Arc::new(source);
+        self.cache = Arc::new(FileCache::new());
+        if self.index.is_some() {
+            self = self.indexed(true);
+        }
+        self
+    }
+}
+
+impl Clone for DataResolver {
+    /// Clones are configured as this resolver, and share its cache of parsed files,
+    /// any index, and any watcher started by [subscribe](DataResolver::subscribe()),
+    /// so that e.g. a context per connection needn't read and watch data afresh.
+    fn clone(&self) -> Self {
+        self.detached()
+    }
 }
 
 impl From<PathBuf> for DataResolver {
     fn from(root: PathBuf) -> Self {
-        Self { root }
+        Self::from(vec![root])
+    }
+}
+
+impl From<Vec<PathBuf>> for DataResolver {
+    /// Creates a resolver from data roots in order, later roots overriding earlier.
+    fn from(roots: Vec<PathBuf>) -> Self {
+        Self {
+            roots,
+            cache: Arc::new(FileCache::new()),
+            defaults: DEFAULTS.into(),
+            formats: Arc::new(DataFormats::default()),
+            index: None,
+            profile: None,
+            profiles: Arc::new([]),
+            #[cfg(feature = "git")]
+            revisions: Default::default(),
+            source: Arc::new(FileSystem),
+            sources: false,
+            strict: false,
+            #[cfg(feature = "watch")]
+            watcher: Default::default(),
+        }
     }
 }
 
@@ -84,10 +632,10 @@ impl From<PathBuf> for DataResolver {
 ///         value: &'a mut serde_yaml::Value,
 ///         data_path: &DataPath,
 ///     ) -> Result<&'a mut serde_yaml::Value, DataResolverError> {
-///         if let Ok(id) = i32::resolve_value(data_path.join("id")) {
+///         if let Some(id) = data_path.ok(i32::resolve_value(data_path.join("id"))) {
 ///             value.merge_at("id", id)?;
 ///         }
-///         if let Ok(name) = String::resolve_value(data_path.join("name")) {
+///         if let Some(name) = data_path.ok(String::resolve_value(data_path.join("name"))) {
 ///             value.merge_at("name", name)?;
 ///         }
 ///         Ok(value)
@@ -104,6 +652,11 @@ pub trait ResolveValue {
     ) -> Result<&'a mut serde_yaml::Value, DataResolverError> {
         Ok(value)
     }
+    /// Names of fields defined with `@confql(arrayIdentifier: true)` in the GraphQL
+    /// schema.  Items of a list merged [ByKey] are matched on these.
+    fn identifier_fields() -> &'static [&'static str] {
+        &[]
+    }
     /// Create a base value from an identifier.  Useful when building an array, where
     /// some fields are defined with `@confql(arrayIdentifier: true)` in the GraphQL
     /// schema.  Then you can pre-populate said fields with a file name or mapping
@@ -111,18 +664,72 @@ pub trait ResolveValue {
     fn init_with_identifier(_identifier: serde_yaml::Value) -> serde_yaml::Value {
         serde_yaml::Value::Null
     }
+    /// Combine the documents found at a single position, `data_path`, into one value.
+    /// The default implementation merges them in order, which suits mappings and
+    /// primitives.
+    fn resolve_documents(
+        documents: Vec<serde_yaml::Value>,
+        _data_path: &DataPath,
+    ) -> Result<serde_yaml::Value, DataResolverError> {
+        merge_documents(documents)
+    }
+    /// Merge the items of any lists within a value of this type, resolved at `data_path`,
+    /// over the [defaults](DataPath::defaults()) left for once every layer's data is
+    /// merged, as when [deferring defaults](DataPath::deferring_defaults()).  The default
+    /// implementation does nothing.  Structs should apply each property's defaults with
+    /// its own type's implementation, at the property's name joined to `data_path`.
+    fn apply_defaults(_value: &mut serde_yaml::Value, _data_path: &DataPath) {}
+    /// Record `file` as the [source](sources) of data just read from it for a value of
+    /// this type, if the type tracks its sources.  The default implementation does
+    /// nothing.  Structs should annotate each property with its own type's
+    /// implementation, and, if declared with `@confql(sources: true)`, their own keys
+    /// with [sources::annotate_sources()].
+    fn annotate_sources(_value: &mut serde_yaml::Value, _file: &str) {}
+    /// Put any lists within a fully resolved value of this type into their final order.
+    /// The default implementation does nothing.  Structs should order each property
+    /// with its own type's implementation, then sort any fields defined with
+    /// `@confql(orderBy: "...")` in the GraphQL schema using [order_by()].
+    fn order_value(_value: &mut serde_yaml::Value) {}
+    /// Merge a more specific value into a less specific one, both representing this type.
+    /// The default implementation uses [Merge::merge()].  Structs should merge each
+    /// property with its own type's (or merge strategy's) implementation, so that
+    /// e.g. a field resolved with [Replace] is replaced wherever it is merged.
+    fn merge_value(
+        value: &mut serde_yaml::Value,
+        mergee: serde_yaml::Value,
+    ) -> Result<&mut serde_yaml::Value, DataResolverError> {
+        value.merge(mergee)
+    }
     /// Resolve data from the given [DataPath].  The default implementation should be sufficient
     /// in most cases.
+    ///
+    /// Values are merged from the least specific level down, so that an [UNSET]
+    /// tombstone removes whatever was inherited from above it.
     fn resolve_value(data_path: DataPath) -> Result<serde_yaml::Value, DataResolverError> {
-        let mut value = data_path.value().unwrap_or(serde_yaml::Value::Null);
-        if data_path.done() {
-            Self::merge_properties(&mut value, &data_path)?;
-        } else if let Some(data_path) = data_path.descend() {
-            if let Ok(mergee) = Self::resolve_value(data_path) {
-                value.merge(mergee)?;
+        let mut value = serde_yaml::Value::Null;
+        let mut extended_at = None;
+        let mut data_path = Some(data_path);
+        while let Some(current) = data_path {
+            let mergee = current
+                .ok(current
+                    .documents_by_file(Self::annotate_sources)
+                    .and_then(|files| resolve_files::<Self>(files, &current)))
+                .unwrap_or(serde_yaml::Value::Null);
+            // the most specific data naming what to extend is blamed for any failure
+            if mergee.get(EXTENDS).is_some() {
+                extended_at = Some(current.clone());
             }
+            Self::merge_value(&mut value, mergee).map_err(|e| current.attribute(e))?;
+            if current.done() {
+                Self::merge_properties(&mut value, &current).map_err(|e| current.attribute(e))?;
+                break;
+            }
+            data_path = current.descend();
+        }
+        match extended_at {
+            Some(data_path) => resolve_extends::<Self>(value, &data_path),
+            None => Ok(value),
         }
-        Ok(value)
     }
     /// Resolve a starting value before data acquisition from actual file
     /// content.  [Null](serde_yaml::Value::Null) (default impl) is a good starting value in most cases,
@@ -142,48 +749,251 @@ impl ResolveValue for ID {}
 impl ResolveValue for String {}
 impl ResolveValue for i32 {}
 impl<T: ResolveValue> ResolveValue for Option<T> {
+    fn annotate_sources(value: &mut serde_yaml::Value, file: &str) {
+        T::annotate_sources(value, file)
+    }
+    fn apply_defaults(value: &mut serde_yaml::Value, data_path: &DataPath) {
+        T::apply_defaults(value, data_path)
+    }
+    fn merge_value(
+        value: &mut serde_yaml::Value,
+        mergee: serde_yaml::Value,
+    ) -> Result<&mut serde_yaml::Value, DataResolverError> {
+        T::merge_value(value, mergee)
+    }
+    fn order_value(value: &mut serde_yaml::Value) {
+        T::order_value(value)
+    }
+    fn resolve_documents(
+        documents: Vec<serde_yaml::Value>,
+        data_path: &DataPath,
+    ) -> Result<serde_yaml::Value, DataResolverError> {
+        T::resolve_documents(documents, data_path)
+    }
     fn resolve_value(data_path: DataPath) -> Result<serde_yaml::Value, DataResolverError> {
-        T::resolve_value(data_path).or(Ok(serde_yaml::Value::Null))
+        Ok(data_path
+            .ok(T::resolve_value(data_path.clone()))
+            .unwrap_or(serde_yaml::Value::Null))
     }
 }
 impl<T: ResolveValue> ResolveValue for Vec<T> {
+    /// Several documents at one position become one list element each, while a
+    /// lone mapping document is taken as items keyed by identifier.  Documents of a
+    /// file above the list, e.g. `index.yml`, each hold the list at its
+    /// [address](DataPath::address()), so any mapping there is taken as keyed items,
+    /// and the items of every document concatenated.
+    fn resolve_documents(
+        mut documents: Vec<serde_yaml::Value>,
+        data_path: &DataPath,
+    ) -> Result<serde_yaml::Value, DataResolverError> {
+        if documents.len() == 1 || !data_path.address().is_empty() {
+            for document in documents.iter_mut() {
+                items_from_mapping::<T>(document, data_path);
+            }
+        }
+        Ok(sequence_documents(documents))
+    }
+    /// Items are [merged by key](ByKey), so that an item described in several places is
+    /// unified into one element, provided `T` has [identifier fields](ResolveValue::identifier_fields()).
+    fn merge_value(
+        value: &mut serde_yaml::Value,
+        mergee: serde_yaml::Value,
+    ) -> Result<&mut serde_yaml::Value, DataResolverError> {
+        ByKey::<Self>::merge_value(value, mergee)
+    }
+    /// Items with [identifier fields](ResolveValue::identifier_fields()) are merged over
+    /// the defaults for items at `data_path`, and their own properties' defaults applied
+    /// at their identifier joined to `data_path`, as for items of a directory.  Other
+    /// items are never deferred, so are left as they are.
+    fn apply_defaults(value: &mut serde_yaml::Value, data_path: &DataPath) {
+        let keys = T::identifier_fields();
+        if keys.is_empty() {
+            return;
+        }
+        if let serde_yaml::Value::Sequence(items) = value {
+            merge_over_defaults::<T>(items, data_path);
+            for item in items.iter_mut() {
+                let identifier = keys
+                    .iter()
+                    .find_map(|key| item.get(key).and_then(serde_yaml::Value::as_str))
+                    .map(str::to_owned);
+                if let Some(identifier) = identifier {
+                    T::apply_defaults(item, &data_path.join(identifier));
+                }
+            }
+        }
+    }
+    /// Annotates each item, whether listed or keyed by identifier.
+    fn annotate_sources(value: &mut serde_yaml::Value, file: &str) {
+        use serde_yaml::Value::{Mapping, Sequence};
+        match value {
+            Mapping(items) => items
+                .iter_mut()
+                .for_each(|(_, item)| T::annotate_sources(item, file)),
+            Sequence(items) => items
+                .iter_mut()
+                .for_each(|item| T::annotate_sources(item, file)),
+            _ => {}
+        }
+    }
+    fn order_value(value: &mut serde_yaml::Value) {
+        if let serde_yaml::Value::Sequence(items) = value {
+            items.iter_mut().for_each(T::order_value);
+        }
+    }
+    /// Assembles list items from mapping entries (keyed by identifier), sequence
+    /// entries and [sub-paths](DataPath::sub_paths()) of `data_path`, in that order,
+    /// merging items which share an identifier, then merges each over whatever it
+    /// [extends](EXTENDS), and that over the [defaults](DataPath::defaults()) for items
+    /// at `data_path`, unless [deferring defaults](DataPath::deferring_defaults()).
+    /// With the `parallel` feature, sub-paths are resolved concurrently, but items keep
+    /// the same order.
     fn merge_properties<'a>(
         value: &'a mut serde_yaml::Value,
         data_path: &DataPath,
     ) -> Result<&'a mut serde_yaml::Value, DataResolverError> {
-        use serde_yaml::Value::{Mapping, Sequence};
-        match value {
-            Mapping(map) => {
-                *value = Sequence(
-                    map.into_iter()
-                        .filter_map(|(k, v)| {
-                            let mut value = T::init_with_identifier(k.clone());
-                            value.merge(v.take()).ok().map(|merged| merged.take())
-                        })
-                        .collect(),
-                );
-                Ok(value)
-            }
-            _ => value.merge(
-                data_path
-                    .sub_paths()
-                    .into_iter()
-                    .filter_map(|dp| {
-                        let mut base_value = T::resolve_vec_base(&dp);
-                        T::resolve_value(dp)
-                            .ok()
-                            .map(|v| match base_value.merge(v) {
-                                Ok(_) => Some(base_value),
-                                _ => None,
-                            })
-                    })
-                    .map(|v| v.unwrap())
-                    .collect(),
-            ),
+        use serde_yaml::Value::Sequence;
+        // items without identifiers are never merged with those of other layers, so
+        // are merged over their defaults, and those of their own lists, straight away
+        let defer = data_path.defers_defaults() && !T::identifier_fields().is_empty();
+        items_from_mapping::<T>(value, data_path);
+        let resolve_item = |dp: DataPath| {
+            let dp = dp.deferring_defaults(defer);
+            let mut item = T::resolve_vec_base(&dp);
+            let v = data_path.ok(T::resolve_value(dp.clone()))?;
+            data_path.ok(T::merge_value(&mut item, v)
+                .map(|merged| merged.take())
+                .map_err(|e| dp.attribute(e)))
+        };
+        #[cfg(feature = "parallel")]
+        let sub_path_items = {
+            use rayon::prelude::*;
+            data_path
+                .sub_paths()
+                .into_par_iter()
+                .filter_map(resolve_item)
+                .collect()
+        };
+        #[cfg(not(feature = "parallel"))]
+        let sub_path_items = data_path
+            .sub_paths()
+            .into_iter()
+            .filter_map(resolve_item)
+            .collect();
+        let mut items = Sequence(vec![]);
+        Self::merge_value(&mut items, value.take())?;
+        Self::merge_value(&mut items, Sequence(sub_path_items))?;
+        if let Sequence(items) = &mut items {
+            for item in items.iter_mut() {
+                // items resolved from files of their own are already extended
+                if item.get(EXTENDS).is_some() {
+                    let extended = resolve_extends::<T>(item.clone(), data_path);
+                    if let Some(extended) =
+                        data_path.ok(extended.map_err(|e| data_path.attribute(e)))
+                    {
+                        *item = extended;
+                    }
+                }
+            }
+            if !defer {
+                merge_over_defaults::<T>(items, data_path);
+            }
+        }
+        *value = items;
+        Ok(value)
+    }
+}
+
+/// Merges each mapping of `items`, resolved at `data_path`, over the
+/// [defaults](DataPath::defaults()) for items there.  Defaults which can't be merged
+/// beneath an item are reported, and the item left as it is.
+fn merge_over_defaults<T: ResolveValue>(items: &mut [serde_yaml::Value], data_path: &DataPath) {
+    let defaults = data_path.defaults();
+    if !defaults.is_mapping() {
+        return;
+    }
+    for item in items.iter_mut().filter(|item| item.is_mapping()) {
+        let mut base = defaults.clone();
+        let merged = T::merge_value(&mut base, item.clone()).map(|_| ());
+        if data_path
+            .ok(merged.map_err(|e| data_path.attribute(e)))
+            .is_some()
+        {
+            *item = base;
         }
     }
 }
 
+/// Merges `value`, resolved at `data_path`, over the data at the address given by its
+/// [EXTENDS] key, if any, e.g. `services/api`, merged across every data root.
+fn resolve_extends<R: ResolveValue + ?Sized>(
+    mut value: serde_yaml::Value,
+    data_path: &DataPath,
+) -> Result<serde_yaml::Value, DataResolverError> {
+    let target = match value
+        .as_mapping_mut()
+        .and_then(|mapping| mapping.remove(serde_yaml::Value::from(EXTENDS)))
+    {
+        Some(target) => {
+            serde_yaml::from_value::<String>(target).map_err(|e| data_path.attribute(e.into()))?
+        }
+        None => return Ok(value),
+    };
+    let address: Vec<&str> = target.split('/').filter(|k| !k.is_empty()).collect();
+    let mut base = serde_yaml::Value::Null;
+    for layer in data_path.extending(&address)? {
+        match R::resolve_value(layer) {
+            Ok(v) => {
+                R::merge_value(&mut base, v)?;
+            }
+            Err(e) if e.is_missing_data() => (),
+            Err(e) => return Err(e),
+        }
+    }
+    if base.is_null() {
+        return Err(DataResolverError::ExtendsNotFound(target));
+    }
+    R::merge_value(&mut base, value)?;
+    Ok(base)
+}
+
+/// Resolves the documents of each data file at `data_path`, then merges the files'
+/// values in order, so that e.g. `a.json` and `a.yml` make one list between them,
+/// rather than one list item each.
+fn resolve_files<R: ResolveValue + ?Sized>(
+    files: Vec<Vec<serde_yaml::Value>>,
+    data_path: &DataPath,
+) -> Result<serde_yaml::Value, DataResolverError> {
+    let mut value = serde_yaml::Value::Null;
+    for documents in files {
+        R::merge_value(&mut value, R::resolve_documents(documents, data_path)?)?;
+    }
+    Ok(value)
+}
+
+/// Converts a mapping of identifiers to items into a sequence of items, with
+/// identifier fields initialized from the keys.  An item which can't be merged over
+/// its identifier is [reported](DataPath::ok()) at `data_path`, and kept as it is.
+fn items_from_mapping<T: ResolveValue>(value: &mut serde_yaml::Value, data_path: &DataPath) {
+    use serde_yaml::Value::{Mapping, Sequence};
+    if let Mapping(map) = value {
+        *value = Sequence(
+            map.into_iter()
+                .map(|(k, v)| {
+                    let mut item = T::init_with_identifier(k.clone());
+                    let merged = T::merge_value(&mut item, v.clone())
+                        .map(|_| ())
+                        .map_err(|e| data_path.attribute(e));
+                    match data_path.ok(merged) {
+                        Some(()) => item,
+                        None => v.take(),
+                    }
+                })
+                .collect(),
+        );
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::values::Merge;
@@ -191,6 +1001,7 @@ mod tests {
     use color_eyre::Result;
     use indoc::indoc;
     use test_files::TestFiles;
+    use test_utils::yaml;
 
     #[derive(Debug, Deserialize, PartialEq)]
     struct MyObj {
@@ -203,10 +1014,10 @@ mod tests {
             value: &'a mut serde_yaml::Value,
             data_path: &DataPath,
         ) -> Result<&'a mut serde_yaml::Value, DataResolverError> {
-            if let Ok(id) = i32::resolve_value(data_path.join("id")) {
+            if let Some(id) = data_path.ok(i32::resolve_value(data_path.join("id"))) {
                 value.merge_at("id", id)?;
             }
-            if let Ok(name) = String::resolve_value(data_path.join("name")) {
+            if let Some(name) = data_path.ok(String::resolve_value(data_path.join("name"))) {
                 value.merge_at("name", name)?;
             }
             Ok(value)
@@ -220,20 +1031,29 @@ mod tests {
     }
 
     impl ResolveValue for MyOtherObj {
+        fn identifier_fields() -> &'static [&'static str] {
+            &["alias"]
+        }
         fn init_with_identifier(identifier: serde_yaml::Value) -> serde_yaml::Value {
             use serde_yaml::{Mapping, Value};
             let mut mapping = Mapping::new();
             mapping.insert(Value::from("alias"), identifier);
             Value::Mapping(mapping)
         }
+        fn resolve_vec_base(data_path: &DataPath) -> serde_yaml::Value {
+            match data_path.file_stem().and_then(|s| s.to_str()) {
+                Some(file_stem) => Self::init_with_identifier(file_stem.into()),
+                None => serde_yaml::Value::Null,
+            }
+        }
         fn merge_properties<'a>(
             value: &'a mut serde_yaml::Value,
             data_path: &DataPath,
         ) -> Result<&'a mut serde_yaml::Value, DataResolverError> {
-            if let Ok(id) = i32::resolve_value(data_path.join("id")) {
+            if let Some(id) = data_path.ok(i32::resolve_value(data_path.join("id"))) {
                 value.merge_at("id", id)?;
             }
-            if let Ok(alias) = String::resolve_value(data_path.join("alias")) {
+            if let Some(alias) = data_path.ok(String::resolve_value(data_path.join("alias"))) {
                 value.merge_at("alias", alias)?;
             }
             Ok(value)
@@ -261,19 +1081,13 @@ mod tests {
         }
     }
 
-    trait GetResolver<'a> {
-        fn data_path(&self, address: &'a [&'a str]) -> DataPath<'a>;
+    trait GetResolver {
         fn resolver(&self) -> DataResolver;
     }
 
-    impl<'a> GetResolver<'a> for TestFiles {
-        fn data_path(&self, address: &'a [&'a str]) -> DataPath<'a> {
-            DataPath::new(self.path().to_path_buf(), address)
-        }
+    impl GetResolver for TestFiles {
         fn resolver(&self) -> DataResolver {
-            DataResolver {
-                root: self.path().to_path_buf(),
-            }
+            DataResolver::from(self.path().to_path_buf())
         }
     }
 
@@ -608,4 +1422,750 @@ mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn resolves_list_from_documents() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks.file(
+            "my_list.yml",
+            indoc! {"
+                ---
+                id: 1
+                alias: Obbo
+                ---
+                id: 2
+                alias: Ali
+            "},
+        );
+        let v: Vec<MyOtherObj> = mocks.resolver().get(&["my_list"])?;
+        assert_eq!(
+            v,
+            vec![
+                MyOtherObj {
+                    id: 1,
+                    alias: "Obbo".to_owned(),
+                },
+                MyOtherObj {
+                    id: 2,
+                    alias: "Ali".to_owned(),
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_object_from_merged_documents() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks.file(
+            "index.yml",
+            indoc! {"
+                ---
+                id: 1
+                name: Objy
+                ---
+                id: 2
+            "},
+        );
+        let v: MyObj = mocks.resolver().get(&[])?;
+        assert_eq!(
+            v,
+            MyObj {
+                id: 2,
+                name: "Objy".to_owned()
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_list_with_merge_strategy() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks
+            .file(
+                "index.yml",
+                indoc! {"
+                ---
+                tags: [a, b]
+            "},
+            )
+            .file(
+                "tags.yml",
+                indoc! {"
+                ---
+                [b, c]
+            "},
+            );
+        let resolver = mocks.resolver();
+        let v: Vec<String> = resolver.get(&["tags"])?;
+        assert_eq!(v, vec!["a", "b", "b", "c"]);
+        let v: Vec<String> = resolver.get_with::<_, Replace<Vec<String>>>(&["tags"])?;
+        assert_eq!(v, vec!["b", "c"]);
+        let v: Vec<String> = resolver.get_with::<_, Unique<Vec<String>>>(&["tags"])?;
+        assert_eq!(v, vec!["a", "b", "c"]);
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_unset_inherited_values() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks
+            .file(
+                "index.yml",
+                indoc! {"
+                ---
+                a:
+                    tags: [a, b]
+                    name: A
+            "},
+            )
+            .file(
+                "a/index.yml",
+                indoc! {"
+                ---
+                tags: !unset
+                name: !unset
+            "},
+            )
+            .file(
+                "a/tags.yml",
+                indoc! {"
+                ---
+                [c]
+            "},
+            );
+        let resolver = mocks.resolver();
+        let v: Vec<String> = resolver.get(&["a", "tags"])?;
+        assert_eq!(v, vec!["c"]);
+        let v: Option<String> = resolver.get(&["a", "name"])?;
+        assert_eq!(v, None);
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_strictly() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks
+            .file(
+                "index.yml",
+                indoc! {"
+                ---
+                my_obj:
+                    id: 1
+                    name: Objy
+            "},
+            )
+            .file(
+                "my_obj/name.yml",
+                indoc! {"
+                ---
+                [Objy
+            "},
+            );
+        let v: MyObj = mocks.resolver().get(&["my_obj"])?;
+        assert_eq!(v.name, "Objy");
+        let resolver = mocks.resolver().strict(true);
+        match resolver.get::<MyObj>(&["my_obj"]) {
+            Err(DataResolverError::ResolutionErrors(errors)) => {
+                assert!(matches!(
+                    &errors[..],
+                    [DataResolverError::AtPath { source, address, .. }]
+                        if matches!(**source, DataResolverError::YamlError(_)) && address.is_empty()
+                ))
+            }
+            _ => panic!("expected resolution errors"),
+        }
+        let v: i32 = resolver.get(&["my_obj", "id"])?;
+        assert_eq!(v, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn caches_parsed_files() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks.file(
+            "index.yml",
+            indoc! {"
+                ---
+                my_obj:
+                    id: 1
+                    name: Objy
+            "},
+        );
+        let resolver = mocks.resolver();
+        let v: MyObj = resolver.get(&["my_obj"])?;
+        assert_eq!(v.name, "Objy");
+        let first = resolver.cache_metrics();
+        resolver.get::<MyObj>(&["my_obj"])?;
+        let second = resolver.cache_metrics();
+        assert_eq!(second.misses, first.misses);
+        assert!(second.hit_rate() > first.hit_rate());
+        mocks.file(
+            "index.yml",
+            indoc! {"
+                ---
+                my_obj:
+                    id: 1
+                    name: Objection
+            "},
+        );
+        let v: MyObj = resolver.get(&["my_obj"])?;
+        assert_eq!(v.name, "Objection");
+        assert!(resolver.cache_metrics().misses > second.misses);
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn gets_asynchronously() -> Result<()> {
+        use futures::executor::block_on;
+        let mocks = TestFiles::new();
+        mocks.file(
+            "my_obj.yml",
+            indoc! {"
+                ---
+                id: 1
+                name: Objy
+            "},
+        );
+        let resolver = mocks.resolver();
+        let v: MyObj = block_on(resolver.get_async(&["my_obj"]))?;
+        assert_eq!(v, resolver.get(&["my_obj"])?);
+        assert!(block_on(resolver.exists_async(&["my_obj", "name"])));
+        assert!(!block_on(resolver.exists_async(&["my_obj", "tags"])));
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_from_memory_source() -> Result<()> {
+        for indexed in [false, true] {
+            let source = MemorySource::new();
+            source
+                .file("/data/index.yml", "my_obj: {id: 1}")
+                .file("/data/my_obj/name.yml", "Objy")
+                .file("/data/objs/b.yml", "{id: 3, name: Bee}")
+                .file("/data/objs/a.yml", "{id: 2, name: Ay}");
+            let resolver = DataResolver::from(PathBuf::from("/data"))
+                .indexed(indexed)
+                .with_source(source);
+            let v: MyObj = resolver.get(&["my_obj"])?;
+            assert_eq!(v.name, "Objy");
+            let v: Vec<MyObj> = resolver.get(&["objs"])?;
+            assert_eq!(v.iter().map(|o| o.id).collect::<Vec<_>>(), vec![2, 3]);
+            assert!(!resolver.exists(&["other"]));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_over_layered_roots() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks
+            .file("base/index.yml", "my_obj: {id: 1, name: Objy}")
+            .file("base/my_list/Ali.yml", "id: 1")
+            .file("base/my_list/Obbo.yml", "id: 2")
+            .file("prod/my_obj.yml", "id: 2")
+            .file("prod/my_list.yml", "{Ali: {id: 3}, Ozzy: {id: 4}}");
+        let roots = vec![mocks.path().join("base"), mocks.path().join("prod")];
+        for indexed in [false, true] {
+            let resolver = DataResolver::from(roots.clone())
+                .indexed(indexed)
+                .track_sources(true);
+            let v: MyObj = resolver.get(&["my_obj"])?;
+            assert_eq!(
+                v,
+                MyObj {
+                    id: 2,
+                    name: "Objy".to_owned(),
+                }
+            );
+            let v: Vec<MyOtherObj> = resolver.get(&["my_list"])?;
+            assert_eq!(
+                v.iter()
+                    .map(|o| (o.alias.as_str(), o.id))
+                    .collect::<Vec<_>>(),
+                vec![("Ali", 3), ("Obbo", 2), ("Ozzy", 4)]
+            );
+            let steps: Vec<_> = resolver
+                .explain(&["my_obj"])
+                .into_iter()
+                .filter(|step| step.exists)
+                .collect();
+            assert_eq!(
+                steps.iter().map(|step| &step.file).collect::<Vec<_>>(),
+                vec![Path::new("base/index.yml"), Path::new("prod/my_obj.yml")]
+            );
+            assert_eq!(steps[1].merged, yaml! {"{id: 2, name: Objy}"});
+            assert!(resolver.get::<i32>(&["other"]).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_from_index() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks
+            .file(
+                "index.yml",
+                indoc! {"
+                ---
+                my_obj:
+                    id: 1
+            "},
+            )
+            .file(
+                "my_obj/name.yml",
+                indoc! {"
+                ---
+                Objy
+            "},
+            );
+        let resolver = mocks.resolver().indexed(true);
+        let v: MyObj = resolver.get(&["my_obj"])?;
+        assert_eq!(v.name, "Objy");
+        mocks.file(
+            "my_obj/id.yml",
+            indoc! {"
+                ---
+                2
+            "},
+        );
+        let v: MyObj = resolver.get(&["my_obj"])?;
+        assert_eq!(v.id, 1);
+        resolver.refresh_index();
+        let v: MyObj = resolver.get(&["my_obj"])?;
+        assert_eq!(v.id, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn attributes_errors_to_files() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks
+            .file(
+                "index.yml",
+                indoc! {"
+                ---
+                a:
+                    b: [1]
+                    c: x
+            "},
+            )
+            .file(
+                "a.yml",
+                indoc! {"
+                ---
+                b: 2
+            "},
+            )
+            .file(
+                "a/c.yml",
+                indoc! {"
+                ---
+                [x
+            "},
+            );
+        let resolver = mocks.resolver().strict(true);
+        let error = resolver.get::<Vec<i32>>(&["a", "b"]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Incompatible merge `Sequence [Number(1)]` <- `Number(2)`\n    file: {}\n    address: b",
+                mocks.path().join("a.yml").display()
+            )
+        );
+        let error = resolver.get::<String>(&["a", "c"]).unwrap_err();
+        let message = error.to_string();
+        let lines: Vec<_> = message.lines().collect();
+        assert_eq!(
+            lines[2..],
+            [
+                format!(
+                    "    file: {}, line 3, column 1",
+                    mocks.path().join("a/c.yml").display()
+                ),
+                "    address: (root)".to_string()
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_list_items_by_identifier_across_files() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks
+            .file(
+                "index.yml",
+                indoc! {"
+                ---
+                my_list:
+                - alias: Ali
+                  id: 2
+            "},
+            )
+            .file(
+                "my_list.yml",
+                indoc! {"
+                ---
+                Obbo:
+                    id: 1
+                Ali:
+                    id: 3
+            "},
+            )
+            .file(
+                "my_list/Obbo.yml",
+                indoc! {"
+                ---
+                id: 4
+            "},
+            );
+        let v: Vec<MyOtherObj> = mocks.resolver().get(&["my_list"])?;
+        assert_eq!(
+            v,
+            vec![
+                MyOtherObj {
+                    id: 3,
+                    alias: "Ali".to_owned(),
+                },
+                MyOtherObj {
+                    id: 4,
+                    alias: "Obbo".to_owned(),
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_list_items_over_defaults() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks
+            .file("_defaults.yml", "id: 1")
+            .file("index.yml", "my_list: [{alias: Ozzy}]")
+            .file("my_list/_defaults.yml", "id: 2")
+            .file("my_list/Ali.yml", "id: 3")
+            .file("my_list/Obbo.yml", "alias: Obbo")
+            .file("other/my_list/Ali.yml", "alias: Ali")
+            .file("other/my_list/_base.yml", "id: 4");
+        let ids = |resolver: &DataResolver, address: &[&str]| -> Result<Vec<(String, i32)>> {
+            let v: Vec<MyOtherObj> = resolver.get(address)?;
+            Ok(v.into_iter().map(|o| (o.alias, o.id)).collect())
+        };
+        let resolver = mocks.resolver();
+        assert_eq!(
+            ids(&resolver, &["my_list"])?,
+            vec![
+                ("Ozzy".to_owned(), 2),
+                ("Ali".to_owned(), 3),
+                ("Obbo".to_owned(), 2)
+            ]
+        );
+        assert_eq!(
+            ids(&resolver, &["other", "my_list"])?,
+            vec![("Ali".to_owned(), 1)]
+        );
+        let resolver = mocks.resolver().with_defaults_file("_base");
+        assert_eq!(
+            ids(&resolver, &["other", "my_list"])?,
+            vec![("Ali".to_owned(), 4)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_extended_data() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks
+            .file(
+                "index.yml",
+                "my_list: [{alias: Ozzy, _extends: my_list/Obbo}]",
+            )
+            .file("my_list/Ali.yml", "_extends: my_list/Obbo")
+            .file("my_list/Obbo.yml", "id: 4")
+            .file("my_obj.yml", "{_extends: /other/obj, name: Objy}")
+            .file("other/obj.yml", "{id: 9, name: Other}");
+        let resolver = mocks.resolver();
+        let v: Vec<MyOtherObj> = resolver.get(&["my_list"])?;
+        assert_eq!(
+            v.into_iter().map(|o| (o.alias, o.id)).collect::<Vec<_>>(),
+            vec![
+                ("Ozzy".to_owned(), 4),
+                ("Ali".to_owned(), 4),
+                ("Obbo".to_owned(), 4)
+            ]
+        );
+        let v: MyObj = resolver.get(&["my_obj"])?;
+        assert_eq!(
+            v,
+            MyObj {
+                id: 9,
+                name: "Objy".to_owned(),
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reports_extends_cycles() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks
+            .file("a.yml", "{_extends: b, id: 1}")
+            .file("b.yml", "{_extends: c/d, name: Bee}")
+            .file("c/d.yml", "_extends: a")
+            .file("e.yml", "_extends: e")
+            .file("f.yml", "_extends: g");
+        let resolver = mocks.resolver();
+        let e = resolver.get::<MyObj>(&["a"]).unwrap_err();
+        assert_eq!(e.to_string(), "Cycle of `_extends`: a -> b -> c/d -> a");
+        let e = resolver.get::<MyObj>(&["e"]).unwrap_err();
+        assert_eq!(e.to_string(), "Cycle of `_extends`: e -> e");
+        let e = resolver.get::<MyObj>(&["f"]).unwrap_err();
+        assert_eq!(e.to_string(), "No data at `_extends: g`");
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_list_of_dotted_items() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks
+            .file("hosts/db.yml", "1")
+            .file("hosts/web01.example.com.yml", "2")
+            .file("hosts/web02.yml", "3")
+            .file("hosts/web02.com.yml", "4");
+        let v: Vec<i32> = mocks.resolver().get(&["hosts"])?;
+        assert_eq!(v, vec![1, 2, 3, 4]);
+        let v: Vec<i32> = mocks.resolver().in_profile("com").get(&["hosts"])?;
+        assert_eq!(v, vec![1, 2, 4]);
+        let v: Vec<i32> = mocks.resolver().with_profiles(["com"]).get(&["hosts"])?;
+        assert_eq!(v, vec![1, 2, 3]);
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_scalar_list_items_without_defaults() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks
+            .file("_defaults.yml", "replicas: 2")
+            .file("tags/a.yml", "x")
+            .file("tags/b.yml", "y");
+        for strict in [false, true] {
+            let v: Vec<String> = mocks.resolver().strict(strict).get(&["tags"])?;
+            assert_eq!(v, vec!["x", "y"]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_list_items_over_layered_defaults() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks
+            .file("base/my_list/Ali.yml", "id: 3")
+            .file("base/my_list/Obbo.yml", "alias: Obbo")
+            .file("prod/my_list/_defaults.yml", "id: 5")
+            .file("prod/my_list/Ali.yml", "alias: Ali");
+        let roots = vec![mocks.path().join("base"), mocks.path().join("prod")];
+        let v: Vec<MyOtherObj> = DataResolver::from(roots).get(&["my_list"])?;
+        assert_eq!(
+            v.iter()
+                .map(|o| (o.alias.as_str(), o.id))
+                .collect::<Vec<_>>(),
+            vec![("Ali", 3), ("Obbo", 5)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reports_extends_cycles_among_list_items() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks
+            .file("my_list/Ali.yml", "_extends: my_list/Obbo")
+            .file("my_list/Obbo.yml", "_extends: my_list/Ali")
+            .file("my_list/Ozzy.yml", "id: 1");
+        for strict in [false, true] {
+            let resolver = mocks.resolver().strict(strict);
+            match resolver.get::<Vec<MyOtherObj>>(&["my_list"]) {
+                Err(DataResolverError::ResolutionErrors(errors)) => {
+                    assert_eq!(errors.len(), 1);
+                    assert!(errors[0].extends_cycle().is_some());
+                }
+                _ => panic!("expected resolution errors"),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_extended_data_over_layered_roots() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks
+            .file("base/other/obj.yml", "{id: 9, name: Other}")
+            .file("prod/other/obj.yml", "id: 10")
+            .file("prod/my_obj.yml", "_extends: other/obj");
+        let roots = vec![mocks.path().join("base"), mocks.path().join("prod")];
+        let v: MyObj = DataResolver::from(roots).get(&["my_obj"])?;
+        assert_eq!(
+            v,
+            MyObj {
+                id: 10,
+                name: "Other".to_owned(),
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn attributes_extends_errors_to_files() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks.file("my_obj.yml", "{_extends: [other], id: 1}");
+        match mocks.resolver().get::<MyObj>(&["my_obj"]) {
+            Err(DataResolverError::AtPath { path, source, .. }) => {
+                assert_eq!(path, mocks.path().join("my_obj.yml"));
+                assert!(matches!(*source, DataResolverError::YamlError(_)));
+            }
+            _ => panic!("expected an error at my_obj.yml"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn reports_unmergeable_mapping_items() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks.file("my_list.yml", "{a: {id: 1}, b: {alias: [c], id: 2}}");
+        let v = mocks.resolver().resolve::<Vec<MyOtherObj>>(&["my_list"])?;
+        assert_eq!(v, yaml! {"[{alias: a, id: 1}, {alias: [c], id: 2}]"});
+        let resolver = mocks.resolver().strict(true);
+        match resolver.resolve::<Vec<MyOtherObj>>(&["my_list"]) {
+            Err(DataResolverError::ResolutionErrors(errors)) => {
+                assert!(matches!(
+                    &errors[..],
+                    [DataResolverError::AtPath { path, source, .. }]
+                        if *path == mocks.path().join("my_list.yml")
+                            && matches!(**source, DataResolverError::IncompatibleYamlMerge { .. })
+                ))
+            }
+            _ => panic!("expected resolution errors"),
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn resolves_list_from_several_spellings_of_a_file() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks
+            .file("my_list.json", r#"{"a": {"id": 2}, "b": {"id": 3}}"#)
+            .file("my_list.yml", "a: {id: 1}");
+        let v: Vec<MyOtherObj> = mocks.resolver().get(&["my_list"])?;
+        assert_eq!(
+            v,
+            vec![
+                MyOtherObj {
+                    id: 1,
+                    alias: "a".to_owned(),
+                },
+                MyOtherObj {
+                    id: 3,
+                    alias: "b".to_owned(),
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_value_over_defaults_without_resolver() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks
+            .file("my_list/_defaults.yml", "id: 2")
+            .file("my_list/Ali.yml", "id: 3")
+            .file("my_list/Obbo.yml", "alias: Obbo");
+        let value = Vec::<MyOtherObj>::resolve_value(DataPath::new(mocks.path(), &["my_list"]))?;
+        assert_eq!(
+            value,
+            yaml! {"
+                ---
+                - {alias: Ali, id: 3}
+                - {alias: Obbo, id: 2}
+            "}
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_list_from_keyed_items_in_documents_above() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks.file(
+            "index.yml",
+            indoc! {"
+                ---
+                my_list:
+                  Ali: {id: 1}
+                ---
+                my_list:
+                  Obbo: {id: 2}
+                  Ugo: {id: 3}
+            "},
+        );
+        let v: Vec<MyOtherObj> = mocks.resolver().get(&["my_list"])?;
+        assert_eq!(
+            v,
+            vec![
+                MyOtherObj {
+                    id: 1,
+                    alias: "Ali".to_owned(),
+                },
+                MyOtherObj {
+                    id: 2,
+                    alias: "Obbo".to_owned(),
+                },
+                MyOtherObj {
+                    id: 3,
+                    alias: "Ugo".to_owned(),
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_list_in_file_name_order() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks.file("b.yml", "1").file("a.yml", "2");
+        let v: Vec<i32> = mocks.resolver().get(&[])?;
+        assert_eq!(v, vec![2, 1]);
+        let mocks = TestFiles::new();
+        mocks
+            .file("my_list/y/index.yml", "id: 1")
+            .file("my_list/x.yml", "id: 2");
+        let v: Vec<MyOtherObj> = mocks.resolver().get(&["my_list"])?;
+        assert_eq!(
+            v,
+            vec![
+                MyOtherObj {
+                    id: 2,
+                    alias: "x".to_owned(),
+                },
+                MyOtherObj {
+                    id: 1,
+                    alias: "y".to_owned(),
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_list_in_order_of_manifest() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks
+            .file("my_list/x.yml", "id: 1")
+            .file("my_list/y.yml", "id: 2")
+            .file("my_list/z/index.yml", "id: 3")
+            .file("my_list/_order.yml", "[z, y]");
+        let v: Vec<MyOtherObj> = mocks.resolver().get(&["my_list"])?;
+        let aliases: Vec<_> = v.iter().map(|item| item.alias.as_str()).collect();
+        assert_eq!(aliases, vec!["z", "y", "x"]);
+        Ok(())
+    }
 }
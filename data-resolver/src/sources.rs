@@ -0,0 +1,117 @@
+//! Data source tracking.
+//!
+//! When a [DataResolver](crate::DataResolver) is [tracking sources](crate::DataResolver::track_sources()),
+//! every mapping read from a data file for an object type declared with
+//! `@confql(sources: true)` is annotated under a [SOURCES] key with the file
+//! providing each of its keys, by the type's [annotate_sources](crate::ResolveValue::annotate_sources()).
+//! Annotations merge like any other data, so that each key ends up listing every
+//! file which set it, in merge order, the last being the one whose value was kept.
+//! Once resolved, annotations are rendered as [FieldSources], ready to deserialize
+//! into the `_sources` field generated for the type.
+use itertools::Itertools;
+use juniper::GraphQLObject;
+use serde::Deserialize;
+use serde_yaml::{Mapping, Value};
+
+/// Key under which a mapping's sources are recorded.
+pub const SOURCES: &str = "_sources";
+
+/// Files from which a field's value was resolved, in merge order.
+#[derive(Clone, Debug, Deserialize, GraphQLObject, PartialEq)]
+pub struct FieldSources {
+    /// Name of the field
+    pub field: String,
+    /// Paths of data files, relative to the data root
+    pub files: Vec<String>,
+}
+
+/// Records `file` as the source of each key of `value`, if it is a mapping.
+pub fn annotate_sources(value: &mut Value, file: &str) {
+    if let Value::Mapping(mapping) = value {
+        let sources: Mapping = mapping
+            .iter()
+            .filter(|(key, _)| !is_sources_key(key))
+            .map(|(key, _)| (key.clone(), Value::Sequence(vec![Value::from(file)])))
+            .collect();
+        if !sources.is_empty() {
+            mapping.insert(Value::from(SOURCES), Value::Mapping(sources));
+        }
+    }
+}
+
+/// Whether a mapping key is the [SOURCES] annotation.
+pub fn is_sources_key(key: &Value) -> bool {
+    key.as_str() == Some(SOURCES)
+}
+
+/// Converts [SOURCES] annotations throughout `value` into sequences of
+/// [FieldSources], listing each file once.
+pub fn render_sources(value: &mut Value) {
+    match value {
+        Value::Mapping(mapping) => {
+            for (key, v) in mapping.iter_mut() {
+                if !is_sources_key(key) {
+                    render_sources(v);
+                } else if let Value::Mapping(sources) = v {
+                    let rendered = render_field_sources(std::mem::take(sources));
+                    *v = rendered;
+                }
+            }
+        }
+        Value::Sequence(items) => items.iter_mut().for_each(render_sources),
+        _ => {}
+    }
+}
+
+/// Renders one mapping's [SOURCES] annotation as a sequence of [FieldSources].
+fn render_field_sources(sources: Mapping) -> Value {
+    Value::Sequence(
+        sources
+            .into_iter()
+            .map(|(field, files)| {
+                let files = match files {
+                    Value::Sequence(files) => files.into_iter().unique().collect(),
+                    _ => vec![],
+                };
+                let mut rendered = Mapping::new();
+                rendered.insert(Value::from("field"), field);
+                rendered.insert(Value::from("files"), Value::Sequence(files));
+                Value::Mapping(rendered)
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Merge;
+    use color_eyre::Result;
+    use test_utils::yaml;
+
+    #[test]
+    fn annotates_and_renders_sources() -> Result<()> {
+        let mut value = yaml! {"{a: 1, b: {c: 2}}"};
+        annotate_sources(&mut value, "index.yml");
+        let mut mergee = yaml! {"{a: 3, d: [{e: 4}]}"};
+        annotate_sources(&mut mergee, "a.yml");
+        value.merge(mergee)?;
+        render_sources(&mut value);
+        assert_eq!(
+            value,
+            yaml! {"
+                ---
+                a: 3
+                b:
+                  c: 2
+                _sources:
+                  - {field: a, files: [index.yml, a.yml]}
+                  - {field: b, files: [index.yml]}
+                  - {field: d, files: [a.yml]}
+                d:
+                  - e: 4
+            "}
+        );
+        Ok(())
+    }
+}
@@ -1,30 +1,125 @@
 use itertools::Itertools;
+use serde_yaml::value::{Tag, TaggedValue};
 use serde_yaml::Value;
+use std::marker::PhantomData;
 use std::path::Path;
 
-use super::DataResolverError;
+use super::{DataFormat, DataPath, DataResolverError, DataSource, ResolveValue};
 
 pub fn take_sub_value_at_address(
     value: &mut Value,
     address: &[&str],
 ) -> Result<Value, DataResolverError> {
     use itertools::FoldWhile::{Continue, Done};
-    return address
+    address
         .iter()
         .fold_while(Ok(value), |acc, i| match acc.unwrap().get_mut(i) {
             Some(v) => Continue(Ok(v)),
             _ => Done(Err(DataResolverError::KeyNotFound(i.to_string()))),
         })
         .into_inner()
-        .map(|v| std::mem::replace(v, Value::Null));
+        .map(|v| std::mem::replace(v, Value::Null))
 }
 
-pub fn value_from_file(path: &Path) -> Result<Value, DataResolverError> {
-    let file = std::fs::File::open(&path)?;
-    let value = serde_yaml::from_reader::<_, Value>(file)?;
+/// Reads the [Value] of each document in a file of `source`, parsing it as `format`.
+pub fn documents_from_file(
+    path: &Path,
+    format: &dyn DataFormat,
+    source: &dyn DataSource,
+) -> Result<Vec<Value>, DataResolverError> {
+    let content = source.read_file(path)?;
+    format.parse_documents(&content)
+}
+
+/// Tag of the tombstone which unsets whatever less specific data it is merged over,
+/// written in YAML as a `!unset` tagged node, e.g. `tags: !unset`.  Merging keeps
+/// tombstones in place, and [strip_unset] removes them once resolution is complete,
+/// so a tombstoned mapping entry is absent and anything else [Null](Value::Null).
+/// Other formats have no tags, so no tombstones.
+pub const UNSET: &str = "!unset";
+
+/// The [UNSET] tombstone.
+pub fn unset() -> Value {
+    Value::Tagged(Box::new(TaggedValue {
+        tag: Tag::new(UNSET),
+        value: Value::Null,
+    }))
+}
+
+/// Whether a YAML tag, as serde_yaml reports it, is [UNSET].
+#[cfg(feature = "yaml")]
+pub(crate) fn is_unset_tag(tag: &str) -> bool {
+    Tag::new(tag) == UNSET
+}
+
+/// Whether a value is the [UNSET] tombstone.
+pub fn is_unset(value: &Value) -> bool {
+    matches!(value, Value::Tagged(tagged) if tagged.tag == UNSET)
+}
+
+/// Removes [UNSET] tombstones from a fully resolved value.
+pub fn strip_unset(value: Value) -> Value {
+    match value {
+        v if is_unset(&v) => Value::Null,
+        Value::Mapping(mapping) => Value::Mapping(
+            mapping
+                .into_iter()
+                .filter(|(_, v)| !is_unset(v))
+                .map(|(k, v)| (k, strip_unset(v)))
+                .collect(),
+        ),
+        Value::Sequence(items) => Value::Sequence(
+            items
+                .into_iter()
+                .filter(|v| !is_unset(v))
+                .map(strip_unset)
+                .collect(),
+        ),
+        v => v,
+    }
+}
+
+/// Merges documents in order into a single [Value].
+pub fn merge_documents(documents: Vec<Value>) -> Result<Value, DataResolverError> {
+    let mut value = Value::Null;
+    for document in documents {
+        value.merge(document)?;
+    }
     Ok(value)
 }
 
+/// Combines documents into a single [Value] suitable for a list.  A lone document
+/// is returned as is.  Otherwise each document becomes one element of the list,
+/// except that sequence documents are concatenated.
+pub fn sequence_documents(mut documents: Vec<Value>) -> Value {
+    if documents.len() == 1 {
+        return documents.remove(0);
+    }
+    Value::Sequence(
+        documents
+            .into_iter()
+            .flat_map(|document| match document {
+                Value::Sequence(list) => list,
+                v => vec![v],
+            })
+            .collect(),
+    )
+}
+
+/// Sorts the items of a sequence by their value at a given key, with items
+/// lacking that key last.  The sort is stable, and non-sequences are left alone.
+pub fn order_by(value: &mut Value, key: &str) {
+    use std::cmp::Ordering;
+    if let Value::Sequence(items) = value {
+        items.sort_by(|a, b| match (a.get(key), b.get(key)) {
+            (Some(a), Some(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        });
+    }
+}
+
 /// Define methods for
 /// * merging one instance of a type into another instance of the same type
 /// * doing the above but instead under a specified key within the target instance
@@ -34,6 +129,17 @@ pub trait Merge {
     fn merge(&mut self, mergee: Self) -> Result<&mut Self, DataResolverError>;
     /// Merge another instance into self at a specified key, mutating self
     fn merge_at(&mut self, key: &str, mergee: Self) -> Result<&mut Self, DataResolverError>;
+    /// Merge another instance into self at a specified key using a given merge
+    /// function, e.g. [ResolveValue::merge_value()], mutating self
+    fn merge_at_with<F>(
+        &mut self,
+        key: &str,
+        mergee: Self,
+        merge: F,
+    ) -> Result<&mut Self, DataResolverError>
+    where
+        Self: Sized,
+        F: FnOnce(&mut Self, Self) -> Result<&mut Self, DataResolverError>;
     /// Take ownership via mutable reference
     fn take(&mut self) -> Self;
 }
@@ -41,18 +147,22 @@ pub trait Merge {
 macro_rules! merge_compat_err {
     ($self:expr, $mergee:expr) => {
         Err(DataResolverError::IncompatibleYamlMerge {
-            dst: $self.clone(),
-            src: $mergee,
+            dst: Box::new($self.clone()),
+            src: Box::new($mergee),
         })
     };
 }
 
 impl Merge for serde_yaml::Value {
     fn merge(&mut self, mut mergee: Self) -> Result<&mut Self, DataResolverError> {
-        use serde_yaml::Value::{Bool, Mapping, Null, Number, Sequence, String};
+        use serde_yaml::Value::{Bool, Mapping, Null, Number, Sequence, String, Tagged};
         if let Null = mergee {
             return Ok(self);
         }
+        if is_unset(self) || is_unset(&mergee) {
+            *self = mergee;
+            return Ok(self);
+        }
         match self {
             Null => {
                 *self = mergee;
@@ -82,6 +192,9 @@ impl Merge for serde_yaml::Value {
                     return merge_compat_err! {self, mergee};
                 }
             }
+            Tagged(_) => {
+                *self = mergee;
+            }
             Mapping(mapping) => {
                 if let Mapping(superimposee) = mergee {
                     for (key, src) in superimposee {
@@ -99,12 +212,23 @@ impl Merge for serde_yaml::Value {
         Ok(self)
     }
     fn merge_at(&mut self, key: &str, mergee: Self) -> Result<&mut Self, DataResolverError> {
+        self.merge_at_with(key, mergee, Self::merge)
+    }
+    fn merge_at_with<F>(
+        &mut self,
+        key: &str,
+        mergee: Self,
+        merge: F,
+    ) -> Result<&mut Self, DataResolverError>
+    where
+        F: FnOnce(&mut Self, Self) -> Result<&mut Self, DataResolverError>,
+    {
         match self {
             Self::Mapping(mapping) => {
                 let key: Self = key.into();
                 match mapping.get_mut(&key) {
                     Some(value) => {
-                        value.merge(mergee)?;
+                        merge(value, mergee)?;
                     }
                     None => {
                         mapping.insert(key, mergee);
@@ -112,7 +236,7 @@ impl Merge for serde_yaml::Value {
                 }
                 Ok(self)
             }
-            Self::Null => {
+            _ if self.is_null() || is_unset(self) => {
                 let mut mapping = serde_yaml::Mapping::new();
                 mapping.insert(key.into(), mergee);
                 *self = Self::Mapping(mapping);
@@ -128,6 +252,127 @@ impl Merge for serde_yaml::Value {
     }
 }
 
+/// Implements all but [merge_value](ResolveValue::merge_value()) by delegating
+/// to the wrapped type.
+macro_rules! delegate_resolve_value {
+    ($inner:ty) => {
+        fn merge_properties<'a>(
+            value: &'a mut Value,
+            data_path: &DataPath,
+        ) -> Result<&'a mut Value, DataResolverError> {
+            <$inner>::merge_properties(value, data_path)
+        }
+        fn identifier_fields() -> &'static [&'static str] {
+            <$inner>::identifier_fields()
+        }
+        fn init_with_identifier(identifier: Value) -> Value {
+            <$inner>::init_with_identifier(identifier)
+        }
+        fn annotate_sources(value: &mut Value, file: &str) {
+            <$inner>::annotate_sources(value, file)
+        }
+        fn apply_defaults(value: &mut Value, data_path: &DataPath) {
+            <$inner>::apply_defaults(value, data_path)
+        }
+        fn order_value(value: &mut Value) {
+            <$inner>::order_value(value)
+        }
+        fn resolve_documents(
+            documents: Vec<Value>,
+            data_path: &DataPath,
+        ) -> Result<Value, DataResolverError> {
+            <$inner>::resolve_documents(documents, data_path)
+        }
+        fn resolve_vec_base(data_path: &DataPath) -> Value {
+            <$inner>::resolve_vec_base(data_path)
+        }
+    };
+}
+
+/// Merge strategy resolving `T`, where more specific values are merged in with
+/// [Merge::merge()], i.e. sequences are concatenated.  Selected with
+/// `@confql(merge: APPEND)` in the GraphQL schema.
+pub struct Append<T>(PhantomData<T>);
+
+impl<T: ResolveValue> ResolveValue for Append<T> {
+    delegate_resolve_value! {T}
+    fn merge_value(value: &mut Value, mergee: Value) -> Result<&mut Value, DataResolverError> {
+        value.merge(mergee)
+    }
+}
+
+/// Merge strategy resolving `T`, where more specific values replace less specific
+/// ones outright.  Selected with `@confql(merge: REPLACE)` in the GraphQL schema.
+pub struct Replace<T>(PhantomData<T>);
+
+impl<T: ResolveValue> ResolveValue for Replace<T> {
+    delegate_resolve_value! {T}
+    fn merge_value(value: &mut Value, mergee: Value) -> Result<&mut Value, DataResolverError> {
+        // serde_yaml sees through tags, so the tombstone would otherwise look null
+        if !mergee.is_null() || is_unset(&mergee) {
+            *value = mergee;
+        }
+        Ok(value)
+    }
+}
+
+/// Merge strategy resolving `T`, where sequences are concatenated, skipping items
+/// equal to one already present.  Selected with `@confql(merge: UNIQUE)` in the
+/// GraphQL schema.
+pub struct Unique<T>(PhantomData<T>);
+
+impl<T: ResolveValue> ResolveValue for Unique<T> {
+    delegate_resolve_value! {T}
+    fn merge_value(value: &mut Value, mergee: Value) -> Result<&mut Value, DataResolverError> {
+        match (value.as_sequence_mut(), mergee) {
+            (Some(list), Value::Sequence(items)) => {
+                for item in items {
+                    if !list.contains(&item) {
+                        list.push(item);
+                    }
+                }
+                Ok(value)
+            }
+            (_, mergee) => value.merge(mergee),
+        }
+    }
+}
+
+/// Merge strategy resolving `Vec<T>`, where items sharing a value for any of `T`'s
+/// [identifier fields](ResolveValue::identifier_fields()) are merged together, and
+/// other items are appended.  Selected with `@confql(merge: BY_KEY)` in the GraphQL
+/// schema.
+pub struct ByKey<T>(PhantomData<T>);
+
+impl<T: ResolveValue> ResolveValue for ByKey<Vec<T>> {
+    delegate_resolve_value! {Vec<T>}
+    fn merge_value(value: &mut Value, mergee: Value) -> Result<&mut Value, DataResolverError> {
+        let keys = T::identifier_fields();
+        match (value.as_sequence_mut(), mergee) {
+            (Some(list), Value::Sequence(items)) => {
+                for item in items {
+                    match list.iter_mut().find(|i| shares_key(i, &item, keys)) {
+                        Some(existing) => {
+                            T::merge_value(existing, item)?;
+                        }
+                        None => list.push(item),
+                    }
+                }
+                Ok(value)
+            }
+            (_, mergee) => value.merge(mergee),
+        }
+    }
+}
+
+/// Whether two values have the same non-null value at any of the given keys.
+fn shares_key(a: &Value, b: &Value, keys: &[&str]) -> bool {
+    keys.iter().any(|k| match (a.get(k), b.get(k)) {
+        (Some(x), Some(y)) => !x.is_null() && x == y,
+        _ => false,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -138,6 +383,7 @@ mod tests {
 
     // TODO beyond just a couple of happy tests
 
+    #[cfg(feature = "yaml")]
     #[test]
     fn gets_value_from_file() -> Result<()> {
         let filename = "index.yml";
@@ -150,12 +396,208 @@ mod tests {
         mocks.file(filename, content);
         let file_path = mocks.path().join(filename);
 
-        let read_value = value_from_file(&file_path)?;
+        let read_value =
+            documents_from_file(&file_path, &crate::formats::Yaml, &crate::FileSystem)?;
+
+        // serialized without the document marker
+        assert_eq!(
+            serde_yaml::to_string(&read_value[0])?,
+            content.trim_start_matches("---\n")
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn gets_value_from_json_file() -> Result<()> {
+        let filename = "index.json";
+        let mocks = TestFiles::new();
+        mocks.file(filename, r#"{"ok": true, "go": "home"}"#);
+        let file_path = mocks.path().join(filename);
+
+        let read_value =
+            documents_from_file(&file_path, &crate::formats::Json, &crate::FileSystem)?;
+
+        assert_eq!(
+            read_value[0],
+            yaml! {"
+            ---
+            ok: true
+            go: home
+        "}
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn merges_documents_in_order() -> Result<()> {
+        let merged = merge_documents(vec![yaml! {"{a: 1, b: 1}"}, yaml! {"{b: 2}"}, Value::Null])?;
+        assert_eq!(merged, yaml! {"{a: 1, b: 2}"});
+        Ok(())
+    }
+
+    #[test]
+    fn sequences_documents() {
+        assert_eq!(sequence_documents(vec![yaml! {"{a: 1}"}]), yaml! {"{a: 1}"});
+        assert_eq!(
+            sequence_documents(vec![yaml! {"{a: 1}"}, yaml! {"[2, 3]"}, yaml! {"4"}]),
+            yaml! {"[{a: 1}, 2, 3, 4]"}
+        );
+    }
+
+    struct Keyed;
+
+    impl ResolveValue for Keyed {
+        fn identifier_fields() -> &'static [&'static str] {
+            &["name"]
+        }
+    }
+
+    #[test]
+    fn merges_with_strategies() -> Result<()> {
+        let merge = |f: fn(&mut Value, Value) -> Result<&mut Value, DataResolverError>| {
+            let mut value = yaml! {"[a, b]"};
+            f(&mut value, yaml! {"[b, c]"}).map(|v| v.take())
+        };
+        assert_eq!(
+            merge(Append::<Vec<String>>::merge_value)?,
+            yaml! {"[a, b, b, c]"}
+        );
+        assert_eq!(
+            merge(Replace::<Vec<String>>::merge_value)?,
+            yaml! {"[b, c]"}
+        );
+        assert_eq!(
+            merge(Unique::<Vec<String>>::merge_value)?,
+            yaml! {"[a, b, c]"}
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn replace_ignores_null() -> Result<()> {
+        let mut value = yaml! {"[a]"};
+        Replace::<Vec<String>>::merge_value(&mut value, Value::Null)?;
+        assert_eq!(value, yaml! {"[a]"});
+        Ok(())
+    }
+
+    #[test]
+    fn unset_removes_scalars() -> Result<()> {
+        let mut value = yaml! {"{a: 1, b: two, c: true}"};
+        value.merge(yaml! {"{a: !unset ~, b: !unset ~}"})?;
+        assert_eq!(strip_unset(value), yaml! {"{c: true}"});
+        let mut value = yaml! {"1"};
+        value.merge(unset())?;
+        assert_eq!(strip_unset(value), Value::Null);
+        Ok(())
+    }
 
-        assert_eq!(serde_yaml::to_string(&read_value)?, content);
+    #[test]
+    fn unset_removes_mappings() -> Result<()> {
+        let mut value = yaml! {"
+            ---
+            a:
+              b: 1
+              c:
+                d: 2
+            e: 3
+        "};
+        value.merge(yaml! {"
+            ---
+            a:
+              c: !unset ~
+              f: {g: !unset ~, h: 4}
+            e: !unset ~
+        "})?;
+        value.merge_at("e", yaml! {"{i: 5}"})?;
+        assert_eq!(
+            strip_unset(value),
+            yaml! {"
+                ---
+                a:
+                  b: 1
+                  f: {h: 4}
+                e: {i: 5}
+            "}
+        );
         Ok(())
     }
 
+    #[test]
+    fn unset_removes_sequences() -> Result<()> {
+        let mut value = yaml! {"{a: [1, 2], b: [3], c: [6]}"};
+        value.merge(yaml! {"{a: !unset ~, b: !unset ~, c: [!unset ~, 7]}"})?;
+        value.merge(yaml! {"{a: [4], c: [8]}"})?;
+        assert_eq!(strip_unset(value), yaml! {"{a: [4], c: [6, 7, 8]}"});
+        for merge in [
+            Replace::<Vec<String>>::merge_value,
+            Unique::<Vec<String>>::merge_value,
+            ByKey::<Vec<Keyed>>::merge_value,
+        ] {
+            let mut value = yaml! {"[a, b]"};
+            merge(&mut value, unset())?;
+            assert_eq!(strip_unset(value), Value::Null);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn merges_by_key() -> Result<()> {
+        let mut value = yaml! {"
+            ---
+            - name: a
+              size: 1
+            - name: b
+              size: 2
+        "};
+        ByKey::<Vec<Keyed>>::merge_value(
+            &mut value,
+            yaml! {"
+                ---
+                - name: b
+                  size: 3
+                - name: c
+                  size: 4
+            "},
+        )?;
+        assert_eq!(
+            value,
+            yaml! {"
+                ---
+                - name: a
+                  size: 1
+                - name: b
+                  size: 3
+                - name: c
+                  size: 4
+            "}
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn orders_by_key() {
+        let mut value = yaml! {"
+            ---
+            - name: c
+            - size: 1
+            - name: a
+            - name: b
+        "};
+        order_by(&mut value, "name");
+        assert_eq!(
+            value,
+            yaml! {"
+                ---
+                - name: a
+                - name: b
+                - name: c
+                - size: 1
+            "}
+        );
+    }
+
     #[test]
     fn takes_sub_value_at_address() -> Result<()> {
         let mut value = yaml! {"
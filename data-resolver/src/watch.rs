@@ -0,0 +1,338 @@
+//! Live reloading.
+//!
+//! A long-running server should reflect the data root as it is now, not as it was
+//! when first read.  [DataResolver::watch()](crate::DataResolver::watch()) watches
+//! the data root for changes, and as they happen drops affected entries from the
+//! resolver's [FileCache], [refreshes](DirIndex::refresh()) its [DirIndex] if it
+//! has one, and emits a [DataChange] to each
+//! [subscriber](DataWatcher::subscribe()).  Changes arriving together, e.g. as a
+//! `git checkout` writes many files, are handled as one.
+//!
+//! [DataResolver::subscribe()](crate::DataResolver::subscribe()) builds on this to
+//! stream the value at a data address each time it changes, as generated for the
+//! fields of a GraphQL `subscription` type.  Available with the `watch` feature.
+use futures::channel::mpsc::{unbounded, UnboundedReceiver};
+use futures::stream::{self, Stream, StreamExt};
+use itertools::Itertools;
+use notify::event::{EventKind, ModifyKind};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use serde_yaml::Value;
+use std::ffi::OsStr;
+use std::future::ready;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{DataResolver, DataResolverError, DirIndex, FileCache, ResolveValue};
+
+/// File stem of data for a directory itself
+const INDEX: &str = "index";
+
+/// Paths changed under a watched data root.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataChange {
+    /// Changed paths, relative to the data root they're under.  Empty if changes were missed, e.g.
+    /// due to a watch error, so anything may have changed.
+    pub paths: Vec<PathBuf>,
+}
+
+impl DataChange {
+    /// Whether any of the changed paths may hold data at `address`, i.e. is a data
+    /// file (or directory) at, above or beneath it.  A file which may be a profile
+    /// overlay, e.g. `a.prod.yml`, is taken to hold data at `a` too.
+    pub fn affects<S: AsRef<str>>(&self, address: &[S]) -> bool {
+        self.paths.is_empty()
+            || self.paths.iter().any(|path| {
+                let mut keys: Vec<_> = path.components().map(|c| c.as_os_str()).collect();
+                let stem = match path.file_stem() {
+                    Some(stem) => stem,
+                    None => return keys_affect(&keys, address),
+                };
+                keys.pop();
+                let base = stem
+                    .to_str()
+                    .and_then(|stem| stem.rsplit_once('.'))
+                    .map(|(base, _)| OsStr::new(base));
+                [Some(stem), base].iter().flatten().any(|stem| {
+                    let mut keys = keys.clone();
+                    if *stem != INDEX {
+                        keys.push(stem);
+                    }
+                    keys_affect(&keys, address)
+                })
+            })
+    }
+}
+
+/// Whether data at `keys` is at, above or beneath `address`.
+fn keys_affect<S: AsRef<str>>(keys: &[&OsStr], address: &[S]) -> bool {
+    keys.iter().zip(address).all(|(key, k)| *key == k.as_ref())
+}
+
+/// Stream of values resolved by [DataResolver::subscribe()](crate::DataResolver::subscribe()).
+pub type ValueStream = Pin<Box<dyn Stream<Item = Result<Value, DataResolverError>> + Send>>;
+
+type Subscribers = Arc<Mutex<Vec<Box<dyn FnMut(&DataChange) -> bool + Send>>>>;
+
+/// Handle on a watched data root, which stops watching when dropped.
+pub struct DataWatcher {
+    _watcher: RecommendedWatcher,
+    subscribers: Subscribers,
+}
+
+impl DataWatcher {
+    fn add_subscriber<F: FnMut(&DataChange) -> bool + Send + 'static>(&self, subscriber: F) {
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(Box::new(subscriber));
+        }
+    }
+    /// Returns a receiver of every [DataChange] from now on.
+    pub fn subscribe(&self) -> Receiver<DataChange> {
+        let (sender, receiver) = channel();
+        self.add_subscriber(move |change| sender.send(change.clone()).is_ok());
+        receiver
+    }
+    /// As [subscribe](DataWatcher::subscribe()), but returning a [Stream].
+    pub fn subscribe_stream(&self) -> UnboundedReceiver<DataChange> {
+        let (sender, receiver) = unbounded();
+        self.add_subscriber(move |change| sender.unbounded_send(change.clone()).is_ok());
+        receiver
+    }
+}
+
+/// How long events are gathered for, from the first of a burst, e.g. of files
+/// written by a `git checkout`, before being handled as one [DataChange].
+const DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// Starts watching each of `roots`, invalidating `cache` and `index` as they change.
+pub(crate) fn watch(
+    roots: &[PathBuf],
+    cache: Arc<FileCache>,
+    index: Option<Arc<DirIndex>>,
+) -> Result<DataWatcher, DataResolverError> {
+    // events are reported at canonical paths, whereas data is read beneath roots
+    // as given, e.g. relative or through a symlink
+    let roots: Vec<(PathBuf, PathBuf)> = roots
+        .iter()
+        .map(|root| Ok((root.canonicalize()?, root.clone())))
+        .collect::<Result<_, std::io::Error>>()?;
+    let (sender, events) = channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        // only fails once the watcher is dropped
+        let _ = sender.send(event);
+    })?;
+    for (root, _) in roots.iter() {
+        watcher.watch(root, RecursiveMode::Recursive)?;
+    }
+    let subscribers = Subscribers::default();
+    {
+        let subscribers = subscribers.clone();
+        thread::spawn(move || {
+            while let Ok(event) = events.recv() {
+                let mut batch = vec![event];
+                let deadline = Instant::now() + DEBOUNCE;
+                while let Ok(event) =
+                    events.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                {
+                    batch.push(event);
+                }
+                let change = match handle(batch, &roots, &cache, index.as_deref()) {
+                    Some(change) => change,
+                    None => continue,
+                };
+                if let Ok(mut subscribers) = subscribers.lock() {
+                    subscribers.retain_mut(|subscriber| subscriber(&change));
+                }
+            }
+        });
+    }
+    Ok(DataWatcher {
+        _watcher: watcher,
+        subscribers,
+    })
+}
+
+/// Invalidates `cache` and `index` for a batch of events, refreshing the index at
+/// most once, and returns the [DataChange] they make, if any.
+fn handle(
+    batch: Vec<notify::Result<Event>>,
+    roots: &[(PathBuf, PathBuf)],
+    cache: &FileCache,
+    index: Option<&DirIndex>,
+) -> Option<DataChange> {
+    let mut paths = vec![];
+    let mut relists = false;
+    let mut missed = false;
+    for event in batch {
+        let event = match event {
+            Ok(event) if matches!(event.kind, EventKind::Access(_)) => continue,
+            Ok(event) => event,
+            Err(_) => {
+                missed = true;
+                continue;
+            }
+        };
+        relists |= matches!(
+            event.kind,
+            EventKind::Any
+                | EventKind::Create(_)
+                | EventKind::Remove(_)
+                | EventKind::Modify(ModifyKind::Name(_))
+        );
+        for path in event.paths {
+            match roots
+                .iter()
+                .find_map(|(canonical, root)| Some((path.strip_prefix(canonical).ok()?, root)))
+            {
+                Some((relative, root)) => {
+                    cache.invalidate(&root.join(relative));
+                    paths.push(relative.to_path_buf());
+                }
+                None => {
+                    cache.invalidate(&path);
+                    paths.push(path);
+                }
+            }
+        }
+    }
+    if missed {
+        cache.clear();
+        paths.clear();
+    } else if !relists && paths.is_empty() {
+        return None;
+    }
+    if missed || relists {
+        if let Some(index) = index {
+            index.refresh();
+        }
+    }
+    Some(DataChange {
+        paths: paths.into_iter().unique().collect(),
+    })
+}
+
+/// Resolves the value at `address` now, and again on each [DataChange] affecting
+/// it, yielding results which differ from the last.  Values are resolved on the pool
+/// of threads set aside for blocking work, so polling never blocks the executor.
+pub(crate) fn subscribe<R: ResolveValue + 'static>(
+    resolver: DataResolver,
+    address: &[&str],
+    changes: UnboundedReceiver<DataChange>,
+) -> ValueStream {
+    let resolver = Arc::new(resolver);
+    let address: Vec<String> = address.iter().map(|k| k.to_string()).collect();
+    let triggers = changes.filter({
+        let address = address.clone();
+        move |change| ready(change.affects(&address))
+    });
+    let mut last: Option<Value> = None;
+    let values = stream::once(ready(()))
+        .chain(triggers.map(|_| ()))
+        .then(move |_| {
+            let resolver = resolver.clone();
+            let address = address.clone();
+            blocking::unblock(move || {
+                let keys: Vec<&str> = address.iter().map(String::as_str).collect();
+                resolver.resolve::<R>(&keys)
+            })
+        })
+        .filter_map(move |result| {
+            ready(match result {
+                Ok(value) if last.as_ref() == Some(&value) => None,
+                Ok(value) => {
+                    last = Some(value.clone());
+                    Some(Ok(value))
+                }
+                Err(e) => {
+                    last = None;
+                    Some(Err(e))
+                }
+            })
+        });
+    Box::pin(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use color_eyre::Result;
+    use futures::executor::block_on_stream;
+    use test_files::TestFiles;
+
+    #[test]
+    fn reloads_changed_files() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks.file("a.yml", "b: 1");
+        let resolver = DataResolver::from(mocks.path().to_path_buf()).indexed(true);
+        let watcher = resolver.watch()?;
+        let changes = watcher.subscribe();
+        assert_eq!(resolver.get::<i32>(&["a", "b"])?, 1);
+
+        mocks.file("a.yml", "b: 2").file("c.yml", "3");
+        let mut changed = vec![];
+        while !(changed.contains(&"a.yml".into()) && changed.contains(&"c.yml".into())) {
+            changed.extend(changes.recv_timeout(Duration::from_secs(5))?.paths);
+        }
+        assert_eq!(resolver.get::<i32>(&["a", "b"])?, 2);
+        assert_eq!(resolver.get::<i32>(&["c"])?, 3);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reloads_changed_files_beneath_symlinked_root() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks.file("data/a.yml", "b: 1");
+        let root = mocks.path().join("link");
+        std::os::unix::fs::symlink(mocks.path().join("data"), &root)?;
+        let resolver = DataResolver::from(root);
+        let watcher = resolver.watch()?;
+        let changes = watcher.subscribe();
+        assert_eq!(resolver.get::<i32>(&["a", "b"])?, 1);
+
+        mocks.file("data/a.yml", "b: 2");
+        while !changes
+            .recv_timeout(Duration::from_secs(5))?
+            .paths
+            .contains(&"a.yml".into())
+        {}
+        assert_eq!(resolver.get::<i32>(&["a", "b"])?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn changes_affect_addresses_at_above_and_beneath() {
+        let change = |path: &str| DataChange {
+            paths: vec![path.into()],
+        };
+        assert!(change("index.yml").affects(&["a", "b"]));
+        assert!(change("a.yml").affects(&["a", "b"]));
+        assert!(change("a/index.json").affects(&["a", "b"]));
+        assert!(change("a/b.yml").affects(&["a", "b"]));
+        assert!(change("a/b/c/d.yml").affects(&["a", "b"]));
+        assert!(!change("c.yml").affects(&["a", "b"]));
+        assert!(!change("a/c/index.yml").affects(&["a", "b"]));
+        assert!(change("a/b.prod.yml").affects(&["a", "b"]));
+        assert!(change("a/index.prod.yml").affects(&["a", "b"]));
+        assert!(!change("a/c.prod.yml").affects(&["a", "b"]));
+        assert!(DataChange { paths: vec![] }.affects(&["a"]));
+    }
+
+    #[test]
+    fn streams_distinct_values() -> Result<()> {
+        let mocks = TestFiles::new();
+        mocks.file("a.yml", "b: 1");
+        let resolver = DataResolver::from(mocks.path().to_path_buf());
+        let mut values = block_on_stream(resolver.clone().subscribe::<i32>(&["a", "b"])?);
+        // the clone's watcher is the resolver's
+        assert!(resolver.watcher.lock().unwrap().is_some());
+        assert_eq!(values.next().transpose()?, Some(Value::from(1)));
+        mocks.file("c.yml", "3").file("a.yml", "b: 1\nd: 4");
+        mocks.file("a.yml", "b: 2");
+        assert_eq!(values.next().transpose()?, Some(Value::from(2)));
+        Ok(())
+    }
+}
@@ -1,15 +1,27 @@
 //! Actix web example
 //!
 //! A simple example integrating with actix-web
+//!
+//! Run with `explain <address>` (e.g. `explain a.b.c`) to print the data files
+//! consulted in resolving a dot separated data address, rather than serve.
+//!
+//! Data is read from the directory `DATA_ROOT`, or merged from several listed in
+//! `DATA_ROOTS`, later directories overriding earlier.
+//!
+//! While serving, the data roots are watched, so changes are served without a restart,
+//! and subscriptions are served over WebSocket at `/subscriptions`.
 #[macro_use]
 extern crate lazy_static;
+use std::collections::HashMap;
 use std::io;
 use std::sync::Arc;
+use std::time::Duration;
 
-use actix_web::{middleware, web, App, Error, HttpResponse, HttpServer};
+use actix_web::{middleware, web, App, Error, HttpRequest, HttpResponse, HttpServer};
 use confql::graphql_schema_from_file;
 use juniper::http::GraphQLRequest;
-use juniper::{EmptyMutation, EmptySubscription};
+use juniper_actix::subscriptions::subscriptions_handler;
+use juniper_graphql_ws::ConnectionConfig;
 
 graphql_schema_from_file!(schema.gql);
 
@@ -22,51 +34,103 @@ lazy_static! {
         .and_then(|p| p.parse().ok())
         .unwrap_or(8080);
     static ref ADDR: String = format!("{}:{}", *BIND_ADDR, *PORT);
-    static ref DATA_ROOT: PathBuf = std::env::var("DATA_ROOT")
-        .map_or_else(|_e| std::env::current_dir().unwrap(), |root| root.into())
-        .canonicalize()
-        .unwrap();
-    static ref CTX: Ctx = Ctx::from(DATA_ROOT.clone());
+    static ref DATA_ROOTS: Vec<PathBuf> = std::env::var("DATA_ROOTS")
+        .map_or_else(
+            |_e| {
+                vec![std::env::var("DATA_ROOT")
+                    .map_or_else(|_e| std::env::current_dir().unwrap(), |root| root.into())]
+            },
+            |roots| roots.split(',').map(PathBuf::from).collect(),
+        )
+        .into_iter()
+        .map(|root| root.canonicalize().unwrap())
+        .collect();
+    static ref CTX: Ctx = Ctx::new(DataResolver::from(DATA_ROOTS.clone()));
 }
 
 async fn graphql(
     st: web::Data<Arc<Schema>>,
     data: web::Json<GraphQLRequest>,
 ) -> Result<HttpResponse, Error> {
-    let user = web::block(move || {
-        let res = data.execute_sync(&st, &CTX);
-        serde_json::to_string(&res)
-    })
-    .await?;
+    // resolvers are async, so data is read without blocking the server's threads
+    let res = data.execute(&st, &CTX).await;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+async fn subscriptions(
+    req: HttpRequest,
+    stream: web::Payload,
+    st: web::Data<Arc<Schema>>,
+) -> Result<HttpResponse, Error> {
+    // each connection needs a context of its own, so give it a clone of the shared
+    // resolver, which reads and watches data once for every connection
+    let config = ConnectionConfig::new(Ctx::new(CTX.data_resolver.clone()))
+        .with_keep_alive_interval(Duration::from_secs(15));
+    subscriptions_handler(req, stream, st.get_ref().clone(), config).await
+}
+
+fn address(dotted: &str) -> Vec<&str> {
+    dotted.split('.').filter(|k| !k.is_empty()).collect()
+}
+
+async fn explain(query: web::Query<HashMap<String, String>>) -> Result<HttpResponse, Error> {
+    let dotted = query.get("address").cloned().unwrap_or_default();
+    let steps =
+        web::block(move || serde_json::to_string(&CTX.data_resolver.explain(&address(&dotted))))
+            .await?;
     Ok(HttpResponse::Ok()
         .content_type("application/json")
-        .body(user))
+        .body(steps))
 }
 
 #[actix_web::main]
 async fn main() -> io::Result<()> {
+    let mut args = std::env::args().skip(1);
+    if let Some("explain") = args.next().as_deref() {
+        let dotted = args.next().unwrap_or_default();
+        for (i, step) in CTX
+            .data_resolver
+            .explain(&address(&dotted))
+            .iter()
+            .enumerate()
+        {
+            println!("{}. {}", i + 1, step);
+        }
+        return Ok(());
+    }
+
     std::env::set_var("RUST_LOG", "info");
     env_logger::init();
 
     // Create Juniper schema
-    let schema = std::sync::Arc::new(Schema::new(
-        Query,
-        EmptyMutation::new(),
-        EmptySubscription::new(),
-    ));
+    let schema = std::sync::Arc::new(schema());
 
     log::info!(
-        "Starting GraphQL endpoint {}/graphql from data path {:?}",
+        "Starting GraphQL endpoint {}/graphql from data paths {:?}",
         *ADDR,
-        *DATA_ROOT
+        *DATA_ROOTS
     );
 
+    // Watch for data changes, for as long as we're serving
+    let watcher = CTX
+        .data_resolver
+        .watch()
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    let changes = watcher.subscribe();
+    std::thread::spawn(move || {
+        for change in changes {
+            log::info!("Data changed: {:?}", change.paths);
+        }
+    });
+
     // Start http server
     HttpServer::new(move || {
         App::new()
             .data(schema.clone())
             .wrap(middleware::Logger::default())
             .service(web::resource("/graphql").route(web::post().to(graphql)))
+            .service(web::resource("/subscriptions").route(web::get().to(subscriptions)))
+            .service(web::resource("/explain").route(web::get().to(explain)))
     })
     .bind(&*ADDR)?
     .run()
@@ -1,7 +1,8 @@
-use graphql_parser::parse_schema;
+use graphql_parser::{parse_schema, Pos};
 use graphql_parser::{query, schema};
 use proc_macro2::TokenStream;
 use quote::{format_ident, quote, ToTokens};
+use std::collections::HashSet;
 use std::convert::TryFrom;
 use std::path::PathBuf;
 use thiserror::Error;
@@ -18,6 +19,13 @@ pub enum CodeGenError {
     SchemaFileReadError(#[from] std::io::Error),
     #[error(transparent)]
     QraphQLError(#[from] graphql_parser::schema::ParseError),
+    #[error("Invalid `@confql({directive})` on field `{field}` at {position} of schema, expected {expected}")]
+    InvalidDirective {
+        field: String,
+        directive: String,
+        position: Pos,
+        expected: String,
+    },
 }
 
 enum SchemaLocation {
@@ -54,6 +62,7 @@ impl CodeGen {
 struct SchemaParse<'a, T: query::Text<'a>> {
     types: Vec<Type<'a, T>>,
     query_type: T::Value,
+    subscription_type: Option<T::Value>,
 }
 
 impl<'a, T> SchemaParse<'a, T>
@@ -69,6 +78,10 @@ where
         }
     }
     fn context(&self) -> TokenStream {
+        let sources = self.types.iter().any(|t| match t {
+            Type::Object(obj) => obj.sources,
+            Type::Query(_) | Type::Subscription(_) => false,
+        });
         quote! {
             struct Ctx {
                 data_resolver: DataResolver
@@ -76,11 +89,14 @@ where
 
             use std::path::PathBuf;
             impl Ctx {
-                fn from<P: Into<PathBuf>>(p: P) -> Self {
+                fn new(data_resolver: DataResolver) -> Self {
                     Self {
-                        data_resolver: DataResolver::from(p.into())
+                        data_resolver: data_resolver.track_sources(#sources)
                     }
                 }
+                fn from<P: Into<PathBuf>>(p: P) -> Self {
+                    Self::new(DataResolver::from(p.into()))
+                }
             }
 
             impl juniper::Context for Ctx {}
@@ -88,10 +104,25 @@ where
     }
     fn root_node(&self) -> TokenStream {
         let query_type = format_ident!("{}", self.query_type.as_ref());
+        let (subscription_type, subscription) = match &self.subscription_type {
+            Some(name) => {
+                let name = format_ident!("{}", name.as_ref());
+                (quote! { #name }, quote! { #name })
+            }
+            None => (
+                quote! { juniper::EmptySubscription<Ctx> },
+                quote! { juniper::EmptySubscription::new() },
+            ),
+        };
         quote! {
             struct Mutation;
 
-            type Schema = juniper::RootNode<'static, #query_type, juniper::EmptyMutation<Ctx>, juniper::EmptySubscription<Ctx>>;
+            type Schema = juniper::RootNode<'static, #query_type, juniper::EmptyMutation<Ctx>, #subscription_type>;
+
+            #[allow(dead_code)]
+            fn schema() -> Schema {
+                Schema::new(#query_type, juniper::EmptyMutation::new(), #subscription)
+            }
         }
     }
 }
@@ -115,18 +146,30 @@ impl<'a, T: query::Text<'a>> TryFrom<schema::Document<'a, T>> for SchemaParse<'a
 
     fn try_from(doc: schema::Document<'a, T>) -> Result<Self, Self::Error> {
         use types::Object;
-        let mut types = Vec::<Object<'a, T>>::new();
+        let mut types = Vec::<Result<Object<'a, T>, CodeGenError>>::new();
         let mut query_type: Option<T::Value> = None;
+        let mut subscription_type: Option<T::Value> = None;
+        let mut asynchronous = false;
+        let mut profiles = false;
 
         use schema::Definition;
         doc.definitions.into_iter().for_each(|def| match def {
             Definition::TypeDefinition(def) => {
-                types.push(Object::from(def));
+                types.push(Object::try_from(def));
             }
-            Definition::SchemaDefinition(schema) => {
-                if query_type.is_none() {
-                    query_type = schema.query;
-                }
+            Definition::SchemaDefinition(schema) if query_type.is_none() => {
+                query_type = schema.query;
+                subscription_type = schema.subscription;
+                let directives = &schema.directives;
+                let enabled = |key: &str| {
+                    directives
+                        .iter()
+                        .filter(|d| d.name.as_ref() == "confql")
+                        .flat_map(|d| d.arguments.iter())
+                        .any(|(k, v)| k.as_ref() == key && matches!(v, query::Value::Boolean(true)))
+                };
+                asynchronous = enabled("async");
+                profiles = enabled("profiles");
             }
             _ => (),
         });
@@ -134,19 +177,36 @@ impl<'a, T: query::Text<'a>> TryFrom<schema::Document<'a, T>> for SchemaParse<'a
         if query_type.is_none() {
             return Err(Self::Error::SchemaMissingQuery);
         }
+        let mut types = types.into_iter().collect::<Result<Vec<_>, _>>()?;
         let query_type = query_type.unwrap();
+        let lazy_types: HashSet<String> = types
+            .iter()
+            .filter(|t| t.lazy)
+            .map(|t| t.name.as_ref().to_owned())
+            .collect();
+        for t in types.iter_mut() {
+            t.asynchronous = asynchronous;
+            t.profiles = profiles;
+            t.mark_lazy_fields(&lazy_types);
+        }
         let types = types
             .into_iter()
             .map(|t| {
-                use Type::{Object, Query};
+                use Type::{Object, Query, Subscription};
                 if t.name == query_type {
                     Query(t)
+                } else if Some(&t.name) == subscription_type.as_ref() {
+                    Subscription(t)
                 } else {
                     Object(t)
                 }
             })
             .collect();
 
-        Ok(Self { query_type, types })
+        Ok(Self {
+            query_type,
+            subscription_type,
+            types,
+        })
     }
 }
@@ -1,33 +1,76 @@
 use graphql_parser::{query, schema};
 use proc_macro2::{Ident, TokenStream};
 use quote::{format_ident, quote, ToTokens};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+
+use crate::code_gen::CodeGenError;
 
 pub struct Field<'a, T: query::Text<'a>> {
     pub name: T::Value,
     field_type: FieldType<'a, T>,
     directives: HashMap<String, query::Value<'a, T>>,
+    /// Merge strategy type from `@confql(merge: ...)`, if specified
+    merge_strategy: Option<Ident>,
+    /// Item field to sort by from `@confql(orderBy: "...")`, if specified
+    order_by: Option<String>,
+    /// Whether this is a single object of a type declared `@confql(lazy: true)`
+    lazy: bool,
 }
 
-impl<'a, T: query::Text<'a>> From<schema::Field<'a, T>> for Field<'a, T> {
-    fn from(field: schema::Field<'a, T>) -> Self {
+/// Values accepted by `@confql(merge: ...)`
+const MERGE_STRATEGIES: &str = "one of APPEND, BY_KEY, REPLACE or UNIQUE";
+
+impl<'a, T: query::Text<'a>> TryFrom<schema::Field<'a, T>> for Field<'a, T> {
+    type Error = CodeGenError;
+
+    fn try_from(field: schema::Field<'a, T>) -> Result<Self, Self::Error> {
         let schema::Field {
+            position,
             name,
             field_type,
             directives,
             ..
         } = field;
-        let directives = directives
+        let directives: HashMap<String, query::Value<'a, T>> = directives
             .into_iter()
             .filter(|d| d.name.as_ref() == "confql")
             .flat_map(|d| d.arguments)
             .map(|(k, v)| (k.as_ref().to_owned(), v))
             .collect();
-        Self {
+        let invalid = |key: &str, expected: &str| CodeGenError::InvalidDirective {
+            field: name.as_ref().to_owned(),
+            directive: format!("{}: {}", key, directives[key]),
+            position,
+            expected: expected.to_owned(),
+        };
+        let merge_strategy = match directives.get("merge") {
+            Some(query::Value::Enum(strategy)) => Some(format_ident!(
+                "{}",
+                match strategy.as_ref() {
+                    "APPEND" => "Append",
+                    "BY_KEY" => "ByKey",
+                    "REPLACE" => "Replace",
+                    "UNIQUE" => "Unique",
+                    _ => return Err(invalid("merge", MERGE_STRATEGIES)),
+                }
+            )),
+            Some(_) => return Err(invalid("merge", MERGE_STRATEGIES)),
+            None => None,
+        };
+        let order_by = match directives.get("orderBy") {
+            Some(query::Value::String(field)) => Some(field.to_owned()),
+            Some(_) => return Err(invalid("orderBy", "a string naming an item field")),
+            None => None,
+        };
+        Ok(Self {
             name,
             field_type: FieldType::from(field_type),
             directives,
-        }
+            merge_strategy,
+            order_by,
+            lazy: false,
+        })
     }
 }
 
@@ -38,6 +81,21 @@ where
     pub fn directive(&self, key: &str) -> Option<&query::Value<'a, T>> {
         self.directives.get(key)
     }
+    /// Notes whether this field is a single object of one of the given lazy types
+    pub fn mark_lazy(&mut self, lazy_types: &HashSet<String>) {
+        self.lazy = matches!(
+            self.field_type.schema_type(),
+            query::Type::NamedType(name) if lazy_types.contains(name.as_ref())
+        );
+    }
+    /// Merge strategy type from `@confql(merge: ...)`, if specified
+    fn merge_strategy(&self) -> Option<Ident> {
+        self.merge_strategy.clone()
+    }
+    /// Item field to sort by from `@confql(orderBy: "...")`, if specified
+    fn order_by(&self) -> Option<String> {
+        self.order_by.clone()
+    }
 }
 
 impl<'a, T> Field<'a, T>
@@ -45,33 +103,276 @@ where
     T: query::Text<'a>,
     T: Clone,
 {
+    /// Type whose [ResolveValue] impl resolves this field, i.e. the inner
+    /// type wrapped in any merge strategy.
+    fn resolve_type(&self) -> TokenStream {
+        let ty = self.field_type.inner_tokens();
+        match self.merge_strategy() {
+            Some(strategy) => quote! { confql::confql_data_resolver::#strategy<#ty> },
+            None => ty,
+        }
+    }
     pub fn merge_line(&self) -> TokenStream {
-        let Field {
+        let name = self.name.as_ref();
+        let ty = self.resolve_type();
+        quote! {
+            if let Some(v) = data_path.ok(<#ty>::resolve_value(data_path.join(#name))) {
+                value.merge_at_with(#name, v, <#ty>::merge_value)?;
+            }
+        }
+    }
+    pub fn merge_value_line(&self) -> TokenStream {
+        let name = self.name.as_ref();
+        let ty = self.resolve_type();
+        quote! {
+            if let Some(v) = mapping.remove(&serde_yaml::Value::from(#name)) {
+                value.merge_at_with(#name, v, <#ty>::merge_value)?;
+            }
+        }
+    }
+    pub fn annotate_line(&self) -> TokenStream {
+        let name = self.name.as_ref();
+        let ty = self.resolve_type();
+        quote! {
+            if let Some(v) = value.get_mut(#name) {
+                <#ty>::annotate_sources(v, file);
+            }
+        }
+    }
+    pub fn defaults_line(&self) -> TokenStream {
+        let name = self.name.as_ref();
+        let ty = self.resolve_type();
+        quote! {
+            if let Some(v) = value.get_mut(#name) {
+                <#ty>::apply_defaults(v, &data_path.join(#name));
+            }
+        }
+    }
+    pub fn order_line(&self) -> TokenStream {
+        let name = self.name.as_ref();
+        let ty = self.resolve_type();
+        let order_by = self.order_by().map(|field| {
+            quote! {
+                confql::confql_data_resolver::order_by(v, #field);
+            }
+        });
+        quote! {
+            if let Some(v) = value.get_mut(#name) {
+                <#ty>::order_value(v);
+                #order_by
+            }
+        }
+    }
+    /// Type to resolve this field's value as, i.e. the [resolve type](Self::resolve_type()),
+    /// optional if nullable.
+    fn value_type(&self) -> TokenStream {
+        let ty = self.resolve_type();
+        match self.field_type {
+            FieldType::Nullable(_) => quote! { Option<#ty> },
+            FieldType::NonNullable(_) => ty,
+        }
+    }
+    /// Statements returning a lazy object from `data`, or `None` if nullable and
+    /// there is no data for it
+    fn lazy_getter(&self, data: TokenStream, asynchronous: bool) -> TokenStream {
+        let ty = self.field_type.inner_tokens();
+        let (exists, awaiting) = method("exists", asynchronous);
+        match self.field_type {
+            FieldType::Nullable(_) => quote! {
+                let data = #data;
+                Ok(match data.#exists(&context.data_resolver)#awaiting {
+                    true => Some(#ty { data }),
+                    false => None,
+                })
+            },
+            FieldType::NonNullable(_) => quote! {
+                Ok(#ty { data: #data })
+            },
+        }
+    }
+    /// Query field, resolving this field's value.  An `asynchronous` resolver awaits
+    /// data without blocking.  With the `git` feature, the field takes an optional
+    /// `asOf` argument, a git revision to resolve data at, and with `profiles`, an
+    /// optional `profile` argument, a profile to resolve data in.
+    pub fn resolver(&self, asynchronous: bool, profiles: bool) -> TokenStream {
+        let Self {
             name, field_type, ..
         } = self;
         let name = name.as_ref();
-        let ty = field_type.inner_tokens();
+        let field_name = format_ident!("{}", name);
+        let asyncness = asynchronous.then(|| quote! { async });
+        let ty = self.value_type();
+        let git = cfg!(feature = "git");
+        // each argument given scopes the resolver further, e.g. to a revision
+        let mut descriptions = vec![];
+        let mut arguments = vec![];
+        let mut scopes = vec![];
+        if git {
+            descriptions.push(quote! {
+                as_of(description = "Git revision (e.g. commit, branch or tag) to read data as of, rather than the working tree")
+            });
+            arguments.push(quote! { as_of: Option<String> });
+            scopes.push(quote! {
+                let scoped = match as_of {
+                    Some(revision) => Some(scoped.as_ref().unwrap_or(&context.data_resolver).at_revision(&revision)?),
+                    None => scoped,
+                };
+            });
+        }
+        if profiles {
+            descriptions.push(quote! {
+                profile(description = "Profile (e.g. prod) whose overlay files to merge over data files")
+            });
+            arguments.push(quote! { profile: Option<String> });
+            scopes.push(quote! {
+                let scoped = match profile {
+                    Some(profile) => Some(scoped.as_ref().unwrap_or(&context.data_resolver).in_profile(&profile)),
+                    None => scoped,
+                };
+            });
+        }
+        let scoped = !scopes.is_empty();
+        let attributes = scoped.then(|| {
+            quote! {
+                #[graphql(arguments(#(#descriptions),*))]
+            }
+        });
+        let data_resolver = match scoped {
+            true => quote! {
+                let scoped: Option<DataResolver> = None;
+                #(#scopes)*
+                #[allow(unused_variables)]
+                let data_resolver = scoped.as_ref().unwrap_or(&context.data_resolver);
+            },
+            false => quote! {
+                #[allow(unused_variables)]
+                let data_resolver = &context.data_resolver;
+            },
+        };
+        let getter = match (self.lazy, self.merge_strategy(), self.order_by()) {
+            (true, _, _) if scoped => {
+                // data for a scoped resolver is resolved up front, rather than lazily
+                // by the context's
+                let inner = self.field_type.inner_tokens();
+                let (resolve, awaiting) = method("resolve", asynchronous);
+                self.lazy_getter(
+                    quote! {
+                        match &scoped {
+                            Some(resolver) => confql::confql_data_resolver::LazyValue::Resolved(
+                                resolver.#resolve::<#inner>(&[#name])#awaiting?
+                            ),
+                            None => confql::confql_data_resolver::LazyValue::at(&[#name]),
+                        }
+                    },
+                    asynchronous,
+                )
+            }
+            (true, _, _) => self.lazy_getter(
+                quote! {
+                    confql::confql_data_resolver::LazyValue::at(&[#name])
+                },
+                asynchronous,
+            ),
+            (false, None, None) => {
+                let (get, awaiting) = method("get", asynchronous);
+                quote! {
+                    Ok(data_resolver.#get(&[#name])#awaiting?)
+                }
+            }
+            (false, Some(_), None) => {
+                let (get_with, awaiting) = method("get_with", asynchronous);
+                quote! {
+                    Ok(data_resolver.#get_with::<_, #ty>(&[#name])#awaiting?)
+                }
+            }
+            (false, _, Some(field)) => {
+                let (resolve, awaiting) = method("resolve", asynchronous);
+                quote! {
+                    let mut value = data_resolver.#resolve::<#ty>(&[#name])#awaiting?;
+                    confql::confql_data_resolver::order_by(&mut value, #field);
+                    Ok(serde_yaml::from_value(value)?)
+                }
+            }
+        };
         quote! {
-            if let Ok(v) = <#ty>::resolve_value(data_path.join(#name)) {
-                value.merge_at(#name, v)?;
+            #attributes
+            #asyncness fn #field_name(context: &Ctx #(, #arguments)*) -> FieldResult<#field_type> {
+                #data_resolver
+                #getter
             }
         }
     }
-    pub fn resolver(&self) -> TokenStream {
+    /// Field of a lazy object, resolving just this field from the object's data
+    pub fn lazy_resolver(&self, asynchronous: bool) -> TokenStream {
         let Self {
             name, field_type, ..
         } = self;
         let name = name.as_ref();
         let field_name = format_ident!("{}", name);
-        let getter = quote! {
-            Ok(context.data_resolver.get(&[#name])?)
+        let asyncness = asynchronous.then(|| quote! { async });
+        let getter = match self.lazy {
+            true => self.lazy_getter(quote! { self.data.child(#name) }, asynchronous),
+            false => {
+                let ty = self.value_type();
+                let (resolve, awaiting) = method("resolve", asynchronous);
+                let order_by = self.order_by().map(|field| {
+                    quote! {
+                        confql::confql_data_resolver::order_by(&mut value, #field);
+                    }
+                });
+                quote! {
+                    #[allow(unused_mut)]
+                    let mut value = self.data.#resolve::<#ty>(&context.data_resolver, #name)#awaiting?;
+                    #order_by
+                    Ok(serde_yaml::from_value(value)?)
+                }
+            }
         };
         quote! {
-            fn #field_name(context: &Ctx) -> FieldResult<#field_type> {
+            #asyncness fn #field_name(&self, context: &Ctx) -> FieldResult<#field_type> {
                 #getter
             }
         }
     }
+    /// Subscription field streaming this field's value each time its data changes
+    pub fn subscription(&self) -> TokenStream {
+        let Self {
+            name, field_type, ..
+        } = self;
+        let name = name.as_ref();
+        let field_name = format_ident!("{}", name);
+        let ty = self.value_type();
+        let order_by = self.order_by().map(|field| {
+            quote! {
+                confql::confql_data_resolver::order_by(&mut value, #field);
+            }
+        });
+        quote! {
+            async fn #field_name(
+                context: &Ctx
+            ) -> FieldResult<std::pin::Pin<Box<dyn confql::futures::Stream<Item = FieldResult<#field_type>> + Send>>> {
+                use confql::futures::StreamExt;
+                let values = context.data_resolver.subscribe::<#ty>(&[#name])?;
+                let values: std::pin::Pin<Box<dyn confql::futures::Stream<Item = _> + Send>> =
+                    Box::pin(values.map(|value| -> FieldResult<#field_type> {
+                        #[allow(unused_mut)]
+                        let mut value = value?;
+                        #order_by
+                        Ok(serde_yaml::from_value(value)?)
+                    }));
+                Ok(values)
+            }
+        }
+    }
+}
+
+/// Name of a data resolver method, and the tokens awaiting its result, for its async
+/// variant if `asynchronous`
+pub fn method(name: &str, asynchronous: bool) -> (Ident, Option<TokenStream>) {
+    match asynchronous {
+        true => (format_ident!("{}_async", name), Some(quote! { .await })),
+        false => (format_ident!("{}", name), None),
+    }
 }
 
 impl<'a, T> ToTokens for Field<'a, T>
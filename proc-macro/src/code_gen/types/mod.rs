@@ -1,19 +1,32 @@
 use graphql_parser::{query, schema};
 use proc_macro2::TokenStream;
 use quote::{format_ident, quote, ToTokens};
+use std::collections::HashSet;
+use std::convert::TryFrom;
 
 mod fields;
 
-use fields::Field;
+use super::CodeGenError;
+use fields::{method, Field};
 
 pub enum Type<'a, T: query::Text<'a>> {
     Object(Object<'a, T>),
     Query(Object<'a, T>),
+    Subscription(Object<'a, T>),
 }
 
 pub struct Object<'a, T: query::Text<'a>> {
     pub name: T::Value,
+    /// Whether resolvers are async, as given by `schema @confql(async: true)`
+    pub asynchronous: bool,
     fields: Vec<Field<'a, T>>,
+    /// Whether `@confql(lazy: true)` is given, resolving fields only as queried
+    pub lazy: bool,
+    /// Whether query fields take a `profile` argument, as given by
+    /// `schema @confql(profiles: true)`
+    pub profiles: bool,
+    /// Whether `@confql(sources: true)` is given, adding a `_sources` field
+    pub sources: bool,
 }
 
 impl<'a, T: query::Text<'a>> Object<'a, T> {
@@ -31,18 +44,43 @@ impl<'a, T: query::Text<'a>> Object<'a, T> {
             false => Some(fields),
         }
     }
+    /// Notes which fields are of one of the given lazy object types
+    pub fn mark_lazy_fields(&mut self, lazy_types: &HashSet<String>) {
+        for field in self.fields.iter_mut() {
+            field.mark_lazy(lazy_types);
+        }
+    }
 }
 
-impl<'a, T: query::Text<'a>> From<schema::TypeDefinition<'a, T>> for Object<'a, T> {
-    fn from(def: schema::TypeDefinition<'a, T>) -> Self {
+impl<'a, T: query::Text<'a>> TryFrom<schema::TypeDefinition<'a, T>> for Object<'a, T> {
+    type Error = CodeGenError;
+
+    fn try_from(def: schema::TypeDefinition<'a, T>) -> Result<Self, Self::Error> {
         use schema::TypeDefinition;
         match def {
             TypeDefinition::Object(obj) => {
-                let fields = obj.fields.into_iter().map(Field::from).collect();
-                Self {
+                let enabled = |key: &str| {
+                    obj.directives
+                        .iter()
+                        .filter(|d| d.name.as_ref() == "confql")
+                        .flat_map(|d| d.arguments.iter())
+                        .any(|(k, v)| k.as_ref() == key && matches!(v, query::Value::Boolean(true)))
+                };
+                let lazy = enabled("lazy");
+                let sources = enabled("sources");
+                let fields = obj
+                    .fields
+                    .into_iter()
+                    .map(Field::try_from)
+                    .collect::<Result<_, _>>()?;
+                Ok(Self {
                     name: obj.name,
+                    asynchronous: false,
                     fields,
-                }
+                    lazy,
+                    profiles: false,
+                    sources,
+                })
             }
             _ => unimplemented! {},
         }
@@ -50,17 +88,15 @@ impl<'a, T: query::Text<'a>> From<schema::TypeDefinition<'a, T>> for Object<'a,
 }
 
 impl<'doc, T: query::Text<'doc>> Type<'doc, T> {
-    fn from_object_definition(def: schema::ObjectType<'doc, T>) -> Self {
-        let fields = def.fields.into_iter().map(Field::from).collect();
-        Self::Object(Object {
-            name: def.name,
-            fields,
-        })
+    fn from_object_definition(def: schema::ObjectType<'doc, T>) -> Result<Self, CodeGenError> {
+        Object::try_from(schema::TypeDefinition::Object(def)).map(Self::Object)
     }
 }
 
-impl<'a, T: query::Text<'a>> From<schema::TypeDefinition<'a, T>> for Type<'a, T> {
-    fn from(def: schema::TypeDefinition<'a, T>) -> Self {
+impl<'a, T: query::Text<'a>> TryFrom<schema::TypeDefinition<'a, T>> for Type<'a, T> {
+    type Error = CodeGenError;
+
+    fn try_from(def: schema::TypeDefinition<'a, T>) -> Result<Self, Self::Error> {
         use schema::TypeDefinition;
         match def {
             TypeDefinition::Object(obj) => Self::from_object_definition(obj),
@@ -79,8 +115,34 @@ where
             Self::Object(obj) => {
                 let name = format_ident!("{}", obj.name.as_ref());
                 let fields = obj.fields.iter();
-                let merge_lines = obj.fields.iter().map(|f| f.merge_line());
+                let mut merge_lines: Vec<_> = obj.fields.iter().map(|f| f.merge_line()).collect();
+                let mut sources_field = None;
+                if obj.sources {
+                    let names = obj.fields.iter().map(|f| f.name.as_ref());
+                    merge_lines.push(quote! {
+                        data_path.merge_sources(value, &[#(#names),*])?;
+                    });
+                    sources_field = (!obj.lazy).then(|| quote! {
+                        #[graphql(name = "_sources", description = "Data files from which each field was resolved, in merge order")]
+                        #[serde(default)]
+                        _sources: Vec<confql::confql_data_resolver::FieldSources>,
+                    });
+                }
+                let merge_value_lines = obj.fields.iter().map(|f| f.merge_value_line());
+                let order_lines = obj.fields.iter().map(|f| f.order_line());
+                let defaults_lines = obj.fields.iter().map(|f| f.defaults_line());
+                let annotate_lines = obj.fields.iter().map(|f| f.annotate_line());
+                let annotate_own = obj.sources.then(|| quote! {
+                    confql::confql_data_resolver::sources::annotate_sources(value, file);
+                });
                 let mut resolve_value_methods = quote! {
+                    fn annotate_sources(value: &mut serde_yaml::Value, file: &str) {
+                        #(#annotate_lines)*
+                        #annotate_own
+                    }
+                    fn apply_defaults(value: &mut serde_yaml::Value, data_path: &DataPath) {
+                        #(#defaults_lines)*
+                    }
                     fn merge_properties<'a>(
                         value: &'a mut serde_yaml::Value,
                         data_path: &DataPath
@@ -88,9 +150,24 @@ where
                         #(#merge_lines)*
                         Ok(value)
                     }
+                    fn merge_value(
+                        value: &mut serde_yaml::Value,
+                        mut mergee: serde_yaml::Value
+                    ) -> Result<&mut serde_yaml::Value, DataResolverError> {
+                        if let serde_yaml::Value::Mapping(mapping) = &mut mergee {
+                            #(#merge_value_lines)*
+                        }
+                        value.merge(mergee)
+                    }
+                    fn order_value(value: &mut serde_yaml::Value) {
+                        #(#order_lines)*
+                    }
                 };
                 if let Some(identifier_fields) = obj.array_identifier_fields() {
                     resolve_value_methods.extend(quote! {
+                        fn identifier_fields() -> &'static [&'static str] {
+                            &[#(#identifier_fields),*]
+                        }
                         fn init_with_identifier(identifier: serde_yaml::Value) -> serde_yaml::Value {
                             use serde_yaml::{Mapping, Value};
                             let mut mapping = Mapping::new();
@@ -110,10 +187,45 @@ where
                         }
                     })
                 }
+                if obj.lazy {
+                    let resolvers = obj.fields.iter().map(|f| f.lazy_resolver(obj.asynchronous));
+                    let sources_resolver = obj.sources.then(|| {
+                        let asyncness = obj.asynchronous.then(|| quote! { async });
+                        let (value, awaiting) = method("value", obj.asynchronous);
+                        quote! {
+                            #[graphql(name = "_sources", description = "Data files from which each field was resolved, in merge order")]
+                            #asyncness fn _sources(&self, context: &Ctx) -> FieldResult<Vec<confql::confql_data_resolver::FieldSources>> {
+                                let value = self.data.#value::<Self>(&context.data_resolver)#awaiting?;
+                                Ok(match value.get(confql::confql_data_resolver::sources::SOURCES) {
+                                    Some(sources) => serde_yaml::from_value(sources.clone())?,
+                                    None => vec![],
+                                })
+                            }
+                        }
+                    });
+                    return tokens.extend(quote! {
+                        #[derive(Deserialize)]
+                        #[serde(transparent)]
+                        struct #name {
+                            data: confql::confql_data_resolver::LazyValue,
+                        }
+
+                        impl ResolveValue for #name {
+                            #resolve_value_methods
+                        }
+
+                        #[graphql_object(context = Ctx)]
+                        impl #name {
+                            #(#resolvers)*
+                            #sources_resolver
+                        }
+                    });
+                }
                 quote! {
                     #[derive(Deserialize)]
                     #[derive(GraphQLObject)]
                     struct #name {
+                    #sources_field
                     #(#fields),*
                     }
 
@@ -124,7 +236,7 @@ where
             }
             Self::Query(obj) => {
                 let name = format_ident!("{}", obj.name.as_ref());
-                let resolvers = obj.fields.iter().map(|f| f.resolver());
+                let resolvers = obj.fields.iter().map(|f| f.resolver(obj.asynchronous, obj.profiles));
                 quote! {
                     struct #name;
 
@@ -134,6 +246,18 @@ where
                     }
                 }
             }
+            Self::Subscription(obj) => {
+                let name = format_ident!("{}", obj.name.as_ref());
+                let subscriptions = obj.fields.iter().map(|f| f.subscription());
+                quote! {
+                    struct #name;
+
+                    #[juniper::graphql_subscription(context = Ctx)]
+                    impl #name {
+                        #(#subscriptions)*
+                    }
+                }
+            }
         });
     }
 }
@@ -7,12 +7,16 @@ extern crate proc_macro;
 mod code_gen;
 
 use code_gen::CodeGen;
+use quote::quote;
 use std::path::PathBuf;
 
 fn generate_code(gen: CodeGen) -> proc_macro::TokenStream {
     match gen.generate_code() {
         Ok(tokens) => tokens.into(),
-        Err(errors) => panic!("{}", errors),
+        Err(errors) => {
+            let message = errors.to_string();
+            quote!(compile_error!(#message);).into()
+        }
     }
 }
 
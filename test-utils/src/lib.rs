@@ -9,6 +9,7 @@ macro_rules! yaml {
 
 #[cfg(test)]
 mod tests {
+    #[allow(unused_imports)]
     use super::*;
     use anyhow::Result;
 